@@ -1,6 +1,6 @@
 use crate::types::*;
 use frame_support::sp_std::prelude::*;
-use product_registry::ProductId;
+use sp_core::H256;
 
 // --- ShipmentBuilder ---
 
@@ -12,8 +12,11 @@ where
 {
     id: ShipmentId,
     owner: AccountId,
-    products: Vec<ProductId>,
+    consignee: AccountId,
+    products: Vec<ShipmentLine<Moment>>,
     registered: Moment,
+    return_of: Option<ShipmentId>,
+    recalled: bool,
 }
 
 impl<AccountId, Moment> ShipmentBuilder<AccountId, Moment>
@@ -31,7 +34,12 @@ where
         self
     }
 
-    pub fn with_products(mut self, products: Vec<ProductId>) -> Self {
+    pub fn consigned_to(mut self, consignee: AccountId) -> Self {
+        self.consignee = consignee;
+        self
+    }
+
+    pub fn with_products(mut self, products: Vec<ShipmentLine<Moment>>) -> Self {
         self.products = products;
         self
     }
@@ -41,14 +49,36 @@ where
         self
     }
 
+    pub fn returning(mut self, original: ShipmentId) -> Self {
+        self.return_of = Some(original);
+        self
+    }
+
+    pub fn recalled(mut self, recalled: bool) -> Self {
+        self.recalled = recalled;
+        self
+    }
+
     pub fn build(self) -> Shipment<AccountId, Moment> {
         Shipment::<AccountId, Moment> {
             id: self.id,
             owner: self.owner,
+            consignee: self.consignee,
             products: self.products,
             registered: self.registered,
             status: ShipmentStatus::Pending,
             delivered: None,
+            damaged: false,
+            return_of: self.return_of,
+            returned_as: None,
+            delivered_quantities: Vec::new(),
+            fulfilment: None,
+            recalled: self.recalled,
+            compliant: true,
+            deviations: 0,
+            transport_mode: None,
+            distance: 0,
+            emissions: None,
         }
     }
 }
@@ -62,7 +92,9 @@ where
     shipment_id: ShipmentId,
     event_type: ShippingEventType,
     location: Option<ReadPoint>,
+    facility: Option<FacilityId>,
     readings: Vec<Reading<Moment>>,
+    annotations: Vec<ReadingAnnotation>,
     timestamp: Moment,
 }
 
@@ -75,7 +107,9 @@ where
             shipment_id: ShipmentId::default(),
             event_type: ShippingEventType::ShipmentPickup,
             location: Option::<ReadPoint>::default(),
+            facility: Option::<FacilityId>::default(),
             readings: Vec::<Reading<Moment>>::default(),
+            annotations: Vec::<ReadingAnnotation>::default(),
             timestamp: Moment::default(),
         }
     }
@@ -100,11 +134,21 @@ where
         self
     }
 
+    pub fn at_facility(mut self, facility: Option<FacilityId>) -> Self {
+        self.facility = facility;
+        self
+    }
+
     pub fn with_readings(mut self, readings: Vec<Reading<Moment>>) -> Self {
         self.readings = readings;
         self
     }
 
+    pub fn with_annotations(mut self, annotations: Vec<ReadingAnnotation>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     pub fn at_time(mut self, timestamp: Moment) -> Self {
         self.timestamp = timestamp;
         self
@@ -115,8 +159,138 @@ where
             event_type: self.event_type,
             shipment_id: self.shipment_id,
             location: self.location,
+            facility: self.facility,
             readings: self.readings,
+            annotations: self.annotations,
             timestamp: self.timestamp,
         }
     }
 }
+
+// --- DeviceBuilder ---
+
+#[derive(Default)]
+pub struct DeviceBuilder<AccountId, Moment>
+where
+    AccountId: Default,
+    Moment: Default,
+{
+    id: DeviceId,
+    owner: AccountId,
+    reading_types: Vec<ReadingType>,
+    public_key: Option<DevicePublicKey>,
+    registered: Moment,
+}
+
+impl<AccountId, Moment> DeviceBuilder<AccountId, Moment>
+where
+    AccountId: Default,
+    Moment: Default,
+{
+    pub fn identified_by(mut self, id: DeviceId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn owned_by(mut self, owner: AccountId) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn supporting(mut self, reading_types: Vec<ReadingType>) -> Self {
+        self.reading_types = reading_types;
+        self
+    }
+
+    pub fn with_public_key(mut self, public_key: Option<DevicePublicKey>) -> Self {
+        self.public_key = public_key;
+        self
+    }
+
+    pub fn registered_at(mut self, registered: Moment) -> Self {
+        self.registered = registered;
+        self
+    }
+
+    pub fn build(self) -> Device<AccountId, Moment> {
+        Device::<AccountId, Moment> {
+            id: self.id,
+            owner: self.owner,
+            reading_types: self.reading_types,
+            public_key: self.public_key,
+            status: DeviceStatus::Active,
+            registered: self.registered,
+        }
+    }
+}
+
+// --- FacilityBuilder ---
+
+pub struct FacilityBuilder<AccountId, Moment>
+where
+    AccountId: Default,
+    Moment: Default,
+{
+    id: FacilityId,
+    owner: AccountId,
+    address_hash: H256,
+    geofence: Geofence,
+    registered: Moment,
+}
+
+impl<AccountId, Moment> Default for FacilityBuilder<AccountId, Moment>
+where
+    AccountId: Default,
+    Moment: Default,
+{
+    fn default() -> Self {
+        FacilityBuilder {
+            id: FacilityId::default(),
+            owner: AccountId::default(),
+            address_hash: H256::default(),
+            geofence: Geofence::Polygon(Vec::new()),
+            registered: Moment::default(),
+        }
+    }
+}
+
+impl<AccountId, Moment> FacilityBuilder<AccountId, Moment>
+where
+    AccountId: Default,
+    Moment: Default,
+{
+    pub fn identified_by(mut self, id: FacilityId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn owned_by(mut self, owner: AccountId) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn located_at(mut self, address_hash: H256) -> Self {
+        self.address_hash = address_hash;
+        self
+    }
+
+    pub fn within(mut self, geofence: Geofence) -> Self {
+        self.geofence = geofence;
+        self
+    }
+
+    pub fn registered_at(mut self, registered: Moment) -> Self {
+        self.registered = registered;
+        self
+    }
+
+    pub fn build(self) -> Facility<AccountId, Moment> {
+        Facility::<AccountId, Moment> {
+            id: self.id,
+            owner: self.owner,
+            address_hash: self.address_hash,
+            geofence: self.geofence,
+            registered: self.registered,
+        }
+    }
+}
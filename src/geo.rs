@@ -0,0 +1,234 @@
+//! Geographic computations on fixed-point coordinates, so that they're deterministic on-chain.
+//!
+//! Distances are great-circle distances on a spherical Earth (haversine formula), rounded down
+//! to the metre. Polygons are tested with the even-odd rule on plain latitude/longitude, which is
+//! accurate enough for facility-sized areas that don't cross the antimeridian. Distances to a route
+//! are computed on an equirectangular projection around the point, which is accurate enough for
+//! the few kilometres of a route corridor.
+//!
+//! Facilities are indexed by the cells of a grid of tenths of a degree (about 11km of latitude)
+//! overlapped by the bounding box of their geofence, so that the facilities around a point can be
+//! found without scanning all of them.
+
+use crate::types::{Coordinate, GeoCell, GeoPoint};
+use fixed::{
+    consts::PI,
+    transcendental::{asin, cos, sin, sqrt},
+    types::I64F64,
+};
+use frame_support::sp_std::prelude::*;
+
+type Wide = I64F64;
+
+// Mean radius of the Earth, in metres
+pub const EARTH_RADIUS: u32 = 6_371_000;
+// Grid cells of the facility index, per degree of latitude or longitude
+pub const CELLS_PER_DEGREE: i32 = 10;
+
+fn radians(degrees: Coordinate) -> Wide {
+    Wide::from_num(degrees) * Wide::from_num(PI) / Wide::from_num(180)
+}
+
+// Great-circle distance between two points, in metres
+pub fn distance(a: &GeoPoint, b: &GeoPoint) -> Option<u32> {
+    let two = Wide::from_num(2);
+    let (lat_a, lat_b) = (radians(a.latitude), radians(b.latitude));
+    let half_lat = (lat_b - lat_a) / two;
+    let half_lon = (radians(b.longitude) - radians(a.longitude)) / two;
+
+    let sin_lat = sin(half_lat);
+    let sin_lon = sin(half_lon);
+    let h = sin_lat * sin_lat + cos(lat_a) * cos(lat_b) * sin_lon * sin_lon;
+    // Rounding errors could push h slightly out of asin's domain
+    let h = h.min(Wide::from_num(1)).max(Wide::from_num(0));
+
+    let root: Wide = sqrt(h).ok()?;
+    let angle: Wide = asin(root).ok()?;
+    (two * Wide::from_num(EARTH_RADIUS) * angle).checked_to_num::<u32>()
+}
+
+// Whether a point lies inside a polygon given by its vertices, in order
+pub fn polygon_contains(vertices: &[GeoPoint], point: &GeoPoint) -> bool {
+    let (x, y) = (
+        Wide::from_num(point.longitude),
+        Wide::from_num(point.latitude),
+    );
+    let mut inside = false;
+    let mut previous = match vertices.last() {
+        Some(vertex) => vertex,
+        None => return false,
+    };
+    for vertex in vertices {
+        let (x_i, y_i) = (
+            Wide::from_num(vertex.longitude),
+            Wide::from_num(vertex.latitude),
+        );
+        let (x_j, y_j) = (
+            Wide::from_num(previous.longitude),
+            Wide::from_num(previous.latitude),
+        );
+        // The edge straddles the point's latitude, so y_i != y_j
+        if (y_i > y) != (y_j > y) && x < (x_j - x_i) * (y - y_i) / (y_j - y_i) + x_i {
+            inside = !inside;
+        }
+        previous = vertex;
+    }
+    inside
+}
+
+// Distance from a point to the closest segment of a route given by its waypoints, in metres
+pub fn distance_to_route(waypoints: &[GeoPoint], point: &GeoPoint) -> Option<u32> {
+    match waypoints {
+        [] => None,
+        [waypoint] => distance(waypoint, point),
+        _ => waypoints
+            .windows(2)
+            .filter_map(|segment| distance_to_segment(&segment[0], &segment[1], point))
+            .min(),
+    }
+}
+
+fn distance_to_segment(a: &GeoPoint, b: &GeoPoint, point: &GeoPoint) -> Option<u32> {
+    // Project the segment on a plane centred on the point, in metres
+    let radius = Wide::from_num(EARTH_RADIUS);
+    let cos_lat = cos(radians(point.latitude));
+    let project = |vertex: &GeoPoint| {
+        (
+            (radians(vertex.longitude) - radians(point.longitude)) * cos_lat * radius,
+            (radians(vertex.latitude) - radians(point.latitude)) * radius,
+        )
+    };
+    let (a_x, a_y) = project(a);
+    let (b_x, b_y) = project(b);
+
+    // Closest point of the segment to the origin, as a fraction of the segment
+    let (d_x, d_y) = (b_x - a_x, b_y - a_y);
+    let length = d_x * d_x + d_y * d_y;
+    let fraction = if length == Wide::from_num(0) {
+        Wide::from_num(0)
+    } else {
+        ((-a_x * d_x - a_y * d_y) / length)
+            .max(Wide::from_num(0))
+            .min(Wide::from_num(1))
+    };
+    let (x, y) = (a_x + fraction * d_x, a_y + fraction * d_y);
+
+    let root: Wide = sqrt(x * x + y * y).ok()?;
+    root.checked_to_num::<u32>()
+}
+
+// Area of a circle, in square metres
+pub fn circle_area(radius: u32) -> Option<u64> {
+    let radius = Wide::from_num(radius);
+    radius
+        .checked_mul(radius)?
+        .checked_mul(Wide::from_num(PI))?
+        .checked_to_num::<u64>()
+}
+
+// Area of a polygon given by its vertices, in square metres (shoelace formula)
+pub fn polygon_area(vertices: &[GeoPoint]) -> Option<u64> {
+    // Project the vertices on a plane centred on the first one, in metres
+    let origin = vertices.first()?;
+    let radius = Wide::from_num(EARTH_RADIUS);
+    let cos_lat = cos(radians(origin.latitude));
+    let project = |vertex: &GeoPoint| {
+        (
+            (radians(vertex.longitude) - radians(origin.longitude)) * cos_lat * radius,
+            (radians(vertex.latitude) - radians(origin.latitude)) * radius,
+        )
+    };
+
+    let mut previous = project(vertices.last()?);
+    let mut sum = Wide::from_num(0);
+    for vertex in vertices {
+        let (x, y) = project(vertex);
+        sum = sum.checked_add(
+            previous
+                .0
+                .checked_mul(y)?
+                .checked_sub(x.checked_mul(previous.1)?)?,
+        )?;
+        previous = (x, y);
+    }
+    (sum.abs() / Wide::from_num(2)).checked_to_num::<u64>()
+}
+
+// South-west & north-east corners of the box bounding a circle, unless it reaches a pole
+pub fn circle_bounds(centre: &GeoPoint, radius: u32) -> Option<(GeoPoint, GeoPoint)> {
+    // Angle subtended by the radius, in degrees
+    let angle = Wide::from_num(radius)
+        .checked_mul(Wide::from_num(180))?
+        .checked_div(Wide::from_num(PI) * Wide::from_num(EARTH_RADIUS))?;
+    let (latitude, longitude) = (
+        Wide::from_num(centre.latitude),
+        Wide::from_num(centre.longitude),
+    );
+
+    // Longitudes are widest at the edge of the circle furthest from the equator
+    let furthest = latitude.abs().checked_add(angle)?;
+    if furthest >= Wide::from_num(90) {
+        return None;
+    }
+    let angle_lon = angle.checked_div(cos(furthest * Wide::from_num(PI) / Wide::from_num(180)))?;
+
+    let corner = |latitude: Wide, longitude: Wide| {
+        Some(GeoPoint {
+            latitude: Coordinate::checked_from_num(latitude)?,
+            longitude: Coordinate::checked_from_num(
+                longitude.max(Wide::from_num(-180)).min(Wide::from_num(180)),
+            )?,
+        })
+    };
+    Some((
+        corner(latitude - angle, longitude.checked_sub(angle_lon)?)?,
+        corner(latitude + angle, longitude.checked_add(angle_lon)?)?,
+    ))
+}
+
+// South-west & north-east corners of the box bounding a polygon given by its vertices
+pub fn polygon_bounds(vertices: &[GeoPoint]) -> Option<(GeoPoint, GeoPoint)> {
+    let latitudes = || vertices.iter().map(|vertex| vertex.latitude);
+    let longitudes = || vertices.iter().map(|vertex| vertex.longitude);
+    Some((
+        GeoPoint {
+            latitude: latitudes().min()?,
+            longitude: longitudes().min()?,
+        },
+        GeoPoint {
+            latitude: latitudes().max()?,
+            longitude: longitudes().max()?,
+        },
+    ))
+}
+
+// Grid cell containing a point
+pub fn cell_of(point: &GeoPoint) -> GeoCell {
+    let cell = |degrees: Coordinate| {
+        (degrees * Coordinate::from_num(CELLS_PER_DEGREE))
+            .floor()
+            .saturating_to_num::<i16>()
+    };
+    (cell(point.latitude), cell(point.longitude))
+}
+
+// Grid cells overlapped by a box given by its south-west & north-east corners, unless there are
+// more than `max` of them
+pub fn cells_between(
+    south_west: &GeoPoint,
+    north_east: &GeoPoint,
+    max: usize,
+) -> Option<Vec<GeoCell>> {
+    let (south, west) = cell_of(south_west);
+    let (north, east) = cell_of(north_east);
+    let rows = i32::from(north) - i32::from(south) + 1;
+    let columns = i32::from(east) - i32::from(west) + 1;
+    if rows <= 0 || columns <= 0 || (rows * columns) as usize > max {
+        return None;
+    }
+    Some(
+        (south..=north)
+            .flat_map(|row| (west..=east).map(move |column| (row, column)))
+            .collect(),
+    )
+}
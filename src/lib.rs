@@ -2,21 +2,31 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::alloc::string::ToString;
+use codec::{alloc::string::ToString, Encode};
 use core::convert::TryInto;
 use frame_support::{
     debug, decl_error, decl_event, decl_module, decl_storage, dispatch, ensure,
-    sp_runtime::offchain::{
-        self as rt_offchain,
-        storage::StorageValueRef,
-        storage_lock::{StorageLock, Time},
+    sp_runtime::{
+        offchain::{
+            self as rt_offchain,
+            storage::StorageValueRef,
+            storage_lock::{StorageLock, Time},
+        },
+        traits::{Saturating, Zero},
+        transaction_validity::{
+            InvalidTransaction, TransactionLongevity, TransactionPriority, TransactionSource,
+            TransactionValidity, ValidTransaction,
+        },
     },
     sp_std::prelude::*,
-    traits::EnsureOrigin,
+    traits::{EnsureOrigin, Get},
+    unsigned::ValidateUnsigned,
+    weights::Weight,
 };
-use frame_system::{self as system, ensure_signed, offchain::SendTransactionTypes};
+use frame_system::{self as system, ensure_none, ensure_signed, offchain::SendTransactionTypes};
 
 use product_registry::ProductId;
+use sp_core::H256;
 
 #[cfg(test)]
 mod mock;
@@ -26,20 +36,49 @@ mod tests;
 
 mod types;
 use crate::types::*;
+pub use crate::types::{CalibrationPolicy, EmissionFactors};
 
 mod builders;
 use crate::builders::*;
 
+pub mod geo;
+pub mod merkle;
+
 // General constraints to limit data size
 // Note: these could also be passed as trait config parameters
 pub const IDENTIFIER_MAX_LENGTH: usize = 10;
+pub const LOT_ID_MAX_LENGTH: usize = 20; // GS1 AI (10)
 pub const SHIPMENT_MAX_PRODUCTS: usize = 10;
+pub const THRESHOLD_MAX_RULES: usize = 10;
+pub const DEVICE_ID_MAX_LENGTH: usize = 36;
+pub const DEVICE_MAX_READING_TYPES: usize = 10;
+pub const SHIPMENT_MAX_DEVICES: usize = 10;
+pub const DEVICE_MAX_CALIBRATIONS: usize = 100;
+pub const READING_TYPE_NAME_MAX_LENGTH: usize = 32;
+pub const FACILITY_ID_LENGTH: usize = 13; // GS1 GLN
+pub const FACILITY_MAX_GEOFENCE_VERTICES: usize = 32;
+pub const FACILITY_MAX_CELLS: usize = 9; // of the facility index's grid (see `geo`)
+pub const CELL_MAX_FACILITIES: usize = 32;
+pub const ROUTE_MAX_WAYPOINTS: usize = 50;
+pub const PAYLOAD_MAX_READINGS: usize = 20;
+pub const UNSIGNED_READINGS_LONGEVITY: TransactionLongevity = 64; // in blocks
 pub const LISTENER_ENDPOINT: &str = "http://localhost:3005";
 pub const LOCK_TIMEOUT_EXPIRATION: u64 = 3000; // in milli-seconds
 
-pub trait Trait: system::Trait + timestamp::Trait + SendTransactionTypes<Call<Self>> {
+pub trait Trait:
+    system::Trait + timestamp::Trait + product_registry::Trait + SendTransactionTypes<Call<Self>>
+{
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     type CreateRoleOrigin: EnsureOrigin<Self::Origin>;
+    type DeliveryConfirmationTimeout: Get<Self::BlockNumber>;
+    type RecallOrigin: EnsureOrigin<Self::Origin>;
+    type UnsignedPriority: Get<TransactionPriority>;
+    type CalibrationPolicy: Get<CalibrationPolicy>;
+    type AdminOrigin: EnsureOrigin<Self::Origin>;
+    type EmissionFactors: Get<EmissionFactors>;
+    type DwellLimit: Get<Self::Moment>;
+    type DwellRadius: Get<u32>;
+    type IdleTimeout: Get<Self::BlockNumber>;
 }
 
 decl_storage! {
@@ -47,12 +86,63 @@ decl_storage! {
         // Shipments
         pub Shipments get(fn shipment_by_id): map hasher(blake2_128_concat) ShipmentId => Option<Shipment<T::AccountId, T::Moment>>;
         pub ShipmentsOfOrganization get(fn shipments_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<ShipmentId>;
+        pub ShipmentsOfProduct get(fn shipments_of_product): map hasher(blake2_128_concat) ProductId => Vec<ShipmentId>;
+        pub ShipmentsOfLot get(fn shipments_of_lot): double_map hasher(blake2_128_concat) ProductId, hasher(blake2_128_concat) LotId => Vec<ShipmentId>;
+
+        // Devices
+        pub Devices get(fn device_by_id): map hasher(blake2_128_concat) DeviceId => Option<Device<T::AccountId, T::Moment>>;
+        pub DevicesOfOrganization get(fn devices_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<DeviceId>;
+        pub DevicesOfShipment get(fn devices_of_shipment): map hasher(blake2_128_concat) ShipmentId => Vec<DeviceId>;
+        pub DeviceNonces get(fn device_nonce): map hasher(blake2_128_concat) DeviceId => u64;
+        pub Calibrations get(fn calibrations_of): map hasher(blake2_128_concat) DeviceId => Vec<Calibration<T::Moment>>;
+
+        // Reading types registered by the admin origin, on top of the built-in ones
+        pub CustomReadingTypes get(fn custom_reading_type): map hasher(twox_64_concat) ReadingTypeId => Option<CustomReadingType>;
+
+        // Plausible values of each reading type, in the canonical unit of its dimension
+        pub PlausibilityBounds get(fn plausibility_bounds): map hasher(blake2_128_concat) ReadingType => Option<ReadingBounds>;
+
+        // Facilities, identified by their GLN, & their geofences
+        pub Facilities get(fn facility_by_id): map hasher(blake2_128_concat) FacilityId => Option<Facility<T::AccountId, T::Moment>>;
+        pub FacilitiesOfOrganization get(fn facilities_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<FacilityId>;
+        // Facilities whose geofence overlaps a cell of the grid
+        pub FacilitiesOfCell get(fn facilities_of_cell): map hasher(blake2_128_concat) GeoCell => Vec<FacilityId>;
+
+        // Cold-chain threshold rules & excursions
+        pub ShipmentRules get(fn shipment_rules): map hasher(blake2_128_concat) ShipmentId => Vec<ThresholdRule>;
+        pub ProductRules get(fn product_rules): map hasher(blake2_128_concat) ProductId => Vec<ThresholdRule>;
+        pub ExcursionsOfShipment get(fn excursions_of_shipment): map hasher(blake2_128_concat) ShipmentId => Vec<Excursion<T::Moment>>;
+        pub BatchesOfShipment get(fn batches_of_shipment): map hasher(blake2_128_concat) ShipmentId => Vec<ReadingBatch<T::Moment>>;
+        pub ReadingAggregates get(fn reading_aggregate): double_map hasher(blake2_128_concat) ShipmentId, hasher(blake2_128_concat) ReadingType => Option<ReadingAggregate<T::Moment>>;
+        pub Certificates get(fn certificate_of): map hasher(blake2_128_concat) ShipmentId => Option<ComplianceCertificate<T::Moment>>;
+
+        // Planned routes of shipments, to detect deviations
+        pub ShipmentRoutes get(fn shipment_route): map hasher(blake2_128_concat) ShipmentId => Option<Route>;
+
+        // Active product recalls, for a whole product or one of its lots
+        pub Recalls get(fn recall_of): map hasher(blake2_128_concat) (ProductId, Option<LotId>) => Option<T::Moment>;
 
         // Shipping events
         pub EventCount get(fn event_count): u128 = 0;
         pub AllEvents get(fn event_by_idx): map hasher(blake2_128_concat) ShippingEventIndex => Option<ShippingEvent<T::Moment>>;
         pub EventsOfShipment get(fn events_of_shipment): map hasher(blake2_128_concat) ShipmentId => Vec<ShippingEventIndex>;
 
+        // Last known position of each shipment, to detect geofence crossings
+        pub LastPositions get(fn last_position_of): map hasher(blake2_128_concat) ShipmentId => Option<LastPosition<T::Moment>>;
+
+        // Idle shipments, that stay at the same place or don't receive any event
+        pub Dwells get(fn dwell_of): map hasher(blake2_128_concat) ShipmentId => Option<Dwell<T::Moment>>;
+        pub LastActivity get(fn last_activity_of): map hasher(blake2_128_concat) ShipmentId => Option<T::BlockNumber>;
+        pub IdleDeadlines get(fn idle_deadlines): map hasher(identity) T::BlockNumber => Vec<ShipmentId>;
+
+        // Deliveries awaiting the consignee's confirmation, by confirmation deadline
+        pub DeliveryDeadlines get(fn delivery_deadlines): map hasher(identity) T::BlockNumber => Vec<ShipmentId>;
+
+        // Chain of custody
+        pub Custodians get(fn custodian_of): map hasher(blake2_128_concat) ShipmentId => Option<T::AccountId>;
+        pub PendingHandovers get(fn pending_handover_of): map hasher(blake2_128_concat) ShipmentId => Option<T::AccountId>;
+        pub CustodyHistory get(fn custody_history): map hasher(blake2_128_concat) ShipmentId => Vec<(T::AccountId, ShippingEventIndex)>;
+
         // Off-chain Worker notifications
         pub OcwNotifications get (fn ocw_notifications): map hasher(identity) T::BlockNumber => Vec<ShippingEventIndex>;
     }
@@ -62,22 +152,111 @@ decl_event!(
     pub enum Event<T>
     where
         AccountId = <T as system::Trait>::AccountId,
+        BlockNumber = <T as system::Trait>::BlockNumber,
+        Moment = <T as timestamp::Trait>::Moment,
     {
         ShipmentRegistered(AccountId, ShipmentId, AccountId),
         ShipmentStatusUpdated(AccountId, ShipmentId, ShippingEventIndex, ShipmentStatus),
+        ShipmentCancelled(AccountId, ShipmentId, CancellationReason),
+        ShipmentDamaged(AccountId, ShipmentId, ShippingEventIndex),
+        ShipmentLost(AccountId, ShipmentId, ShippingEventIndex),
+        ShipmentDelayed(AccountId, ShipmentId, ShippingEventIndex),
+        ShipmentReturnInitiated(AccountId, ShipmentId, ShipmentId),
+        CustodyOffered(AccountId, ShipmentId, AccountId),
+        CustodyTransferred(AccountId, ShipmentId, AccountId),
+        DeliveryConfirmed(AccountId, ShipmentId, ShippingEventIndex),
+        DeliveryConfirmationTimedOut(ShipmentId, ShippingEventIndex),
+        DeliveryDisputed(AccountId, ShipmentId, ShippingEventIndex),
+        ProductRecalled(ProductId, Option<LotId>, Vec<ShipmentId>),
+        RecallCleared(ProductId, Option<LotId>, Vec<ShipmentId>),
+        ShipmentRulesUpdated(AccountId, ShipmentId),
+        ProductRulesUpdated(AccountId, ProductId),
+        ExcursionDetected(ShipmentId, ShippingEventIndex, ReadingType, Decimal),
+        ComplianceCertificateIssued(ShipmentId, bool),
+        DeviceRegistered(AccountId, DeviceId, AccountId),
+        DeviceDecommissioned(AccountId, DeviceId),
+        DeviceAttached(AccountId, ShipmentId, DeviceId),
+        DeviceCalibrated(AccountId, DeviceId, u32),
+        ReadingsAnchored(AccountId, ShipmentId, u32, H256),
+        PlausibilityBoundsUpdated(ReadingType, Option<ReadingBounds>),
+        ReadingTypeRegistered(ReadingTypeId, Dimension),
+        FacilityRegistered(AccountId, FacilityId, AccountId),
+        ShipmentArrived(ShipmentId, FacilityId, ShippingEventIndex),
+        ShipmentDeparted(ShipmentId, FacilityId, ShippingEventIndex),
+        ShipmentRouteUpdated(AccountId, ShipmentId),
+        RouteDeviation(ShipmentId, ShippingEventIndex, u32),
+        ShipmentTransportModeUpdated(AccountId, ShipmentId, TransportMode),
+        EmissionsEstimated(ShipmentId, TransportMode, u64),
+        ShipmentIdle(ShipmentId, Moment),
+        ShipmentInactive(ShipmentId, BlockNumber),
     }
 );
 
 decl_error! {
     pub enum Error for Module<T: Trait> {
+        AreaHasTooManyFacilities,
+        DeviceAlreadyExists,
+        DeviceCalibrationHasExpired,
+        DeviceDoesNotSupportReadingType,
+        DeviceHasTooManyCalibrations,
+        DeviceHasTooManyReadingTypes,
+        DeviceIsAlreadyAttached,
+        DeviceIsDecommissioned,
+        DeviceIsNotAttachedToShipment,
+        DeviceIsUnknown,
+        FacilityAlreadyExists,
+        FacilityIsUnknown,
+        GeofenceIsTooLarge,
+        InvalidCalibration,
+        InvalidDeliveryNote,
+        InvalidGeofence,
+        InvalidLocation,
         InvalidOrMissingIdentifier,
+        InvalidReadingBatch,
+        InvalidReadingBounds,
+        InvalidRoute,
+        InvalidThresholdRule,
+        LocationIsOutsideFacility,
+        NoPendingHandover,
+        ProductIsNotOwnedByOrganization,
+        ProductIsUnknown,
+        ReadingIsImplausible,
+        ReadingSignatureIsInvalid,
+        ReadingTimestampIsInvalid,
+        ReadingTypeAlreadyExists,
+        ReadingTypeIsUnknown,
+        ReadingUnitDoesNotMatchType,
+        ReadingsPayloadHasTooManyReadings,
+        ReadingsPayloadNonceIsInvalid,
+        ReadingsPayloadSignatureIsInvalid,
+        RecallAlreadyExists,
+        RecallIsUnknown,
+        SenderIsNotConsignee,
+        SenderIsNotConsigneeOrOwner,
+        SenderIsNotCustodian,
+        SenderIsNotDeviceOwner,
+        SenderIsNotHandoverReceiver,
+        SenderIsNotOwnerOrCustodian,
         ShipmentAlreadyExists,
         ShipmentHasBeenDelivered,
+        ShipmentHasBeenCancelled,
+        ShipmentHasBeenReturned,
+        ShipmentHasExpiredProducts,
+        ShipmentHasInvalidQuantity,
+        ShipmentIsAwaitingConfirmation,
+        ShipmentIsDisputed,
         ShipmentIsInTransit,
+        ShipmentIsLost,
+        ShipmentIsNotAwaitingConfirmation,
+        ShipmentIsNotDelivered,
+        ShipmentIsPending,
+        ShipmentIsRecalled,
         ShipmentIsUnknown,
         ShipmentHasTooManyProducts,
+        ShipmentHasTooManyDevices,
         ShippingEventAlreadyExists,
         ShippingEventMaxExceeded,
+        TooManyThresholdRules,
         OffchainWorkerAlreadyBusy
     }
 }
@@ -88,24 +267,26 @@ decl_module! {
         fn deposit_event() = default;
 
         #[weight = 10_000]
-        pub fn register_shipment(origin, id: ShipmentId, owner: T::AccountId, products: Vec<ProductId>) -> dispatch::DispatchResult {
-            T::CreateRoleOrigin::ensure_origin(origin.clone())?;
+        pub fn register_shipment(origin, id: ShipmentId, owner: T::AccountId, consignee: T::AccountId, products: Vec<ShipmentLine<T::Moment>>) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
             let who = ensure_signed(origin)?;
 
             // Validate format of shipment ID
             Self::validate_identifier(&id)?;
 
-            // Validate shipment products
-            Self::validate_shipment_products(&products)?;
+            // Validate shipment products (1 DB read per product)
+            Self::validate_shipment_products(&owner, &products)?;
 
             // Check shipment doesn't exist yet (1 DB read)
             Self::validate_new_shipment(&id)?;
 
-            // Create a shipment instance
+            // Create a shipment instance, flagged if it carries recalled products
             let shipment = Self::new_shipment()
                 .identified_by(id.clone())
                 .owned_by(owner.clone())
+                .consigned_to(consignee)
                 .registered_at(<timestamp::Module<T>>::now())
+                .recalled(Self::is_recalled(&products))
                 .with_products(products)
                 .build();
             let status = shipment.status.clone();
@@ -121,11 +302,14 @@ decl_module! {
 
             // Storage writes
             // --------------
-            // Add shipment (2 DB write)
+            // Add shipment (2 DB write, 1 DB read & write per product & lot)
+            Self::index_shipment_products(&shipment);
             <Shipments<T>>::insert(&id, shipment);
             <ShipmentsOfOrganization<T>>::append(&owner, &id);
-            // Store shipping event (1 DB read, 3 DB writes)
+            // Store shipping event (2 DB reads, 5 DB writes)
             let event_idx = Self::store_event(event)?;
+            // Owner has initial custody of the shipment (2 DB writes)
+            Self::set_custodian(&id, owner.clone(), event_idx);
             // Update offchain notifications (1 DB write)
             <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
 
@@ -143,206 +327,2009 @@ decl_module! {
             operation: ShippingOperation,
             #[compact] timestamp: T::Moment,
             location: Option<ReadPoint>,
-            readings: Option<Vec<Reading<T::Moment>>>
+            readings: Option<Vec<Reading<T::Moment>>>,
+            delivery_note: Option<DeliveryNote>,
+            facility: Option<FacilityId>
+        ) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // A delivery note can only be provided upon delivery
+            ensure!(
+                delivery_note.is_none() || operation == ShippingOperation::Deliver,
+                <Error<T>>::InvalidDeliveryNote
+            );
+
+            // Check shipment is known (1 DB read)
+            let shipment = <Shipments<T>>::get(&id).ok_or(<Error<T>>::ShipmentIsUnknown)?;
+
+            // Only the current custodian can handle the shipment, except that anyone finding a
+            // lost shipment can scan it (1 DB read)
+            if operation != ShippingOperation::Scan || shipment.status != ShipmentStatus::Lost {
+                Self::validate_custodian(&id, &who)?;
+            }
+
+            let input = ShippingEventInput {
+                timestamp,
+                location,
+                readings: readings.unwrap_or_default(),
+                delivery_note,
+                facility,
+            };
+            Self::do_track_shipment(who, shipment, operation, input)?;
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn submit_readings_unsigned(
+            origin,
+            payload: ReadingsPayload<T::Moment>,
+            signature: DeviceSignature
         ) -> dispatch::DispatchResult {
-            T::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            ensure_none(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&payload.shipment_id)?;
+
+            // Payload must be signed by an active device attached to the shipment, & the readings
+            // of other devices must be signed by them (2 DB reads + 1 DB read per such reading)
+            let device = Self::validate_readings_payload(&payload, &signature)?;
+
+            // Payload must not be replayed (1 DB read)
+            let nonce = <DeviceNonces>::get(&payload.signer);
+            ensure!(payload.nonce == nonce, <Error<T>>::ReadingsPayloadNonceIsInvalid);
+
+            // Check shipment is known (1 DB read)
+            let shipment = <Shipments<T>>::get(&payload.shipment_id)
+                .ok_or(<Error<T>>::ShipmentIsUnknown)?;
+
+            // Storage writes
+            // --------------
+            // Consume nonce, even if the readings are rejected (1 DB write)
+            <DeviceNonces>::insert(&payload.signer, nonce.saturating_add(1));
+
+            // Readings are recorded on behalf of the device's owner
+            let input = ShippingEventInput {
+                timestamp: payload.timestamp,
+                location: payload.location,
+                readings: payload.readings,
+                delivery_note: None,
+                facility: None,
+            };
+            Self::do_track_shipment(device.owner, shipment, ShippingOperation::Scan, input)?;
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn cancel_shipment(origin, id: ShipmentId, reason: CancellationReason) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
             let who = ensure_signed(origin)?;
 
             // Validate format of shipment ID
             Self::validate_identifier(&id)?;
 
-            // Check shipment is known (1 DB read) & do transition checks
-            let mut shipment = match <Shipments<T>>::get(&id) {
+            // Check shipment is known (1 DB read) & hasn't been picked up yet
+            let shipment = match <Shipments<T>>::get(&id) {
                 Some(shipment) => match shipment.status {
+                    ShipmentStatus::Pending => Ok(shipment),
+                    ShipmentStatus::AwaitingConfirmation => Err(<Error<T>>::ShipmentIsAwaitingConfirmation),
                     ShipmentStatus::Delivered => Err(<Error<T>>::ShipmentHasBeenDelivered),
-                    ShipmentStatus::InTransit if operation == ShippingOperation::Pickup =>
-                        Err(<Error<T>>::ShipmentIsInTransit),
-                    _ => Ok(shipment)
+                    ShipmentStatus::Cancelled => Err(<Error<T>>::ShipmentHasBeenCancelled),
+                    ShipmentStatus::Disputed => Err(<Error<T>>::ShipmentIsDisputed),
+                    _ => Err(<Error<T>>::ShipmentIsInTransit),
                 }
                 None => Err(<Error<T>>::ShipmentIsUnknown)
             }?;
 
+            // Only the shipment's owner or current custodian can cancel it (1 DB read)
+            Self::validate_owner_or_custodian(&shipment, &who)?;
+
             // Update shipment status
-            shipment = match operation {
-                ShippingOperation::Pickup => shipment.pickup(),
-                ShippingOperation::Deliver => shipment.deliver(timestamp),
-                _ => shipment,
-            };
+            let shipment = shipment.cancel();
             let status = shipment.status.clone();
 
             // Create shipping event
             let event = Self::new_shipping_event()
-                .of_type(operation.clone().into())
+                .of_type(ShippingEventType::ShipmentCancellation(reason.clone()))
                 .for_shipment(id.clone())
-                .at_location(location)
-                .with_readings(readings.unwrap_or_default())
-                .at_time(timestamp)
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(<timestamp::Module<T>>::now())
                 .build();
 
             // Storage writes
             // --------------
-            // Store shipping event (1 DB read, 3 DB writes)
+            // Store shipping event (2 DB reads, 5 DB writes)
             let event_idx = Self::store_event(event)?;
             // Update offchain notifications (1 DB write)
             <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+            // Update shipment (1 DB write)
+            <Shipments<T>>::insert(&id, shipment);
 
-            if operation != ShippingOperation::Scan {
-                // Update shipment (1 DB write)
-                <Shipments<T>>::insert(&id, shipment);
-                // Raise events
-                Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, status));
-            }
+            // Raise events
+            Self::deposit_event(RawEvent::ShipmentCancelled(who.clone(), id.clone(), reason));
+            Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, status));
 
             Ok(())
         }
 
-        fn offchain_worker(block_number: T::BlockNumber) {
-            // Acquiring the lock
-            let mut lock = StorageLock::<Time>::with_deadline(
-                b"product_tracking_ocw::lock",
-                rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION)
+        #[weight = 10_000]
+        pub fn initiate_return(origin, original_id: ShipmentId, id: ShipmentId, owner: T::AccountId) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of original & return shipment IDs
+            Self::validate_identifier(&original_id)?;
+            Self::validate_identifier(&id)?;
+
+            // Check original shipment is known (1 DB read), has been delivered (or its delivery
+            // disputed, e.g. refused goods) & not returned yet
+            let original = match <Shipments<T>>::get(&original_id) {
+                Some(shipment) => match shipment.status {
+                    ShipmentStatus::Delivered | ShipmentStatus::Disputed if shipment.returned_as.is_some() =>
+                        Err(<Error<T>>::ShipmentHasBeenReturned),
+                    ShipmentStatus::Delivered | ShipmentStatus::Disputed => Ok(shipment),
+                    ShipmentStatus::Cancelled => Err(<Error<T>>::ShipmentHasBeenCancelled),
+                    _ => Err(<Error<T>>::ShipmentIsNotDelivered),
+                }
+                None => Err(<Error<T>>::ShipmentIsUnknown)
+            }?;
+
+            // Only the original shipment's consignee or owner can return it
+            ensure!(
+                who == original.consignee || who == original.owner,
+                <Error<T>>::SenderIsNotConsigneeOrOwner
             );
 
-            match lock.try_lock() {
-                Ok(_guard) => { Self::process_ocw_notifications(block_number); }
-                Err(_err) => { debug::info!("[product_tracking_ocw] lock is already acquired"); }
-            };
-        }
-    }
-}
+            // Check return shipment doesn't exist yet (1 DB read)
+            Self::validate_new_shipment(&id)?;
 
-impl<T: Trait> Module<T> {
-    // Helper methods
-    fn new_shipment() -> ShipmentBuilder<T::AccountId, T::Moment> {
-        ShipmentBuilder::<T::AccountId, T::Moment>::default()
-    }
+            // Create the return shipment, carrying the original shipment's products
+            // back to the original shipment's owner
+            let now = <timestamp::Module<T>>::now();
+            let shipment = Self::new_shipment()
+                .identified_by(id.clone())
+                .owned_by(owner.clone())
+                .consigned_to(original.owner.clone())
+                .registered_at(now)
+                .recalled(Self::is_recalled(&original.products))
+                .with_products(original.products.clone())
+                .returning(original_id.clone())
+                .build();
+            let status = shipment.status.clone();
+            let original = original.returned_as(id.clone());
 
-    fn new_shipping_event() -> ShippingEventBuilder<T::Moment> {
-        ShippingEventBuilder::<T::Moment>::default()
-    }
+            // Create shipping events
+            let return_event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentReturn)
+                .for_shipment(original_id.clone())
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(now)
+                .build();
+            let event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentRegistration)
+                .for_shipment(id.clone())
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(now)
+                .build();
 
-    fn store_event(event: ShippingEvent<T::Moment>) -> Result<ShippingEventIndex, Error<T>> {
-        let event_idx = EventCount::get()
-            .checked_add(1)
-            .ok_or(Error::<T>::ShippingEventMaxExceeded)?;
+            // Storage writes
+            // --------------
+            // Update original shipment, add return shipment (3 DB write, 1 DB read & write per product & lot)
+            <Shipments<T>>::insert(&original_id, original);
+            Self::index_shipment_products(&shipment);
+            <Shipments<T>>::insert(&id, shipment);
+            <ShipmentsOfOrganization<T>>::append(&owner, &id);
+            // Store shipping events (4 DB reads, 10 DB writes)
+            let return_event_idx = Self::store_event(return_event)?;
+            let event_idx = Self::store_event(event)?;
+            // Owner has initial custody of the return shipment (2 DB writes)
+            Self::set_custodian(&id, owner.clone(), event_idx);
+            // Update offchain notifications (2 DB write)
+            let block_number = <system::Module<T>>::block_number();
+            <OcwNotifications<T>>::append(block_number, return_event_idx);
+            <OcwNotifications<T>>::append(block_number, event_idx);
 
-        EventCount::put(event_idx);
-        EventsOfShipment::append(&event.shipment_id, event_idx);
-        <AllEvents<T>>::insert(event_idx, event);
+            // Raise events
+            Self::deposit_event(RawEvent::ShipmentReturnInitiated(who.clone(), original_id, id.clone()));
+            Self::deposit_event(RawEvent::ShipmentRegistered(who.clone(), id.clone(), owner));
+            Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, status));
 
-        Ok(event_idx)
-    }
+            Ok(())
+        }
 
-    // (Public) Validation methods
-    pub fn validate_identifier(id: &[u8]) -> Result<(), Error<T>> {
-        // Basic identifier validation
-        ensure!(!id.is_empty(), Error::<T>::InvalidOrMissingIdentifier);
-        ensure!(
-            id.len() <= IDENTIFIER_MAX_LENGTH,
-            Error::<T>::InvalidOrMissingIdentifier
-        );
-        Ok(())
-    }
+        #[weight = 10_000]
+        pub fn offer_custody(origin, id: ShipmentId, receiver: T::AccountId) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
 
-    pub fn validate_new_shipment(id: &[u8]) -> Result<(), Error<T>> {
-        // Shipment existence check
-        ensure!(
-            !<Shipments<T>>::contains_key(id),
-            Error::<T>::ShipmentAlreadyExists
-        );
-        Ok(())
-    }
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
 
-    pub fn validate_shipment_products(props: &[ProductId]) -> Result<(), Error<T>> {
-        ensure!(
-            props.len() <= SHIPMENT_MAX_PRODUCTS,
-            Error::<T>::ShipmentHasTooManyProducts,
-        );
-        Ok(())
-    }
+            // Check shipment is known & can still change hands (1 DB read)
+            Self::validate_active_shipment(&id)?;
 
-    // --- Offchain worker methods ---
+            // Only the current custodian can hand the shipment over (1 DB read)
+            Self::validate_custodian(&id, &who)?;
 
-    fn process_ocw_notifications(block_number: T::BlockNumber) {
-        // Check last processed block
-        let last_processed_block_ref =
-            StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block");
-        let mut last_processed_block: u32 = match last_processed_block_ref.get::<T::BlockNumber>() {
-            Some(Some(last_proccessed_block)) if last_proccessed_block >= block_number => {
-                debug::info!(
-                    "[product_tracking_ocw] Skipping: Block {:?} has already been processed.",
-                    block_number
-                );
-                return;
-            }
-            Some(Some(last_proccessed_block)) => {
-                last_proccessed_block.try_into().ok().unwrap() as u32
-            }
-            None => 0u32, //TODO: define a OCW_MAX_BACKTRACK_PERIOD param
-            _ => {
-                debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::last_proccessed_block.");
-                return;
-            }
-        };
+            // Create shipping event
+            let event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentHandoverOffer)
+                .for_shipment(id.clone())
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(<timestamp::Module<T>>::now())
+                .build();
 
-        let start_block = last_processed_block + 1;
-        let end_block = block_number.try_into().ok().unwrap() as u32;
-        for current_block in start_block..end_block {
-            debug::debug!(
-                "[product_tracking_ocw] Processing notifications for block {}",
-                current_block
-            );
-            let ev_indices = Self::ocw_notifications::<T::BlockNumber>(current_block.into());
+            // Storage writes
+            // --------------
+            // Store shipping event (2 DB reads, 5 DB writes)
+            let event_idx = Self::store_event(event)?;
+            // Record pending handover (1 DB write)
+            <PendingHandovers<T>>::insert(&id, &receiver);
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
 
-            let listener_results: Result<Vec<_>, _> = ev_indices
-                .iter()
-                .map(|idx| match Self::event_by_idx(idx) {
-                    Some(ev) => Self::notify_listener(&ev),
-                    None => Ok(()),
-                })
-                .collect();
+            // Raise events
+            Self::deposit_event(RawEvent::CustodyOffered(who, id, receiver));
 
-            if let Err(err) = listener_results {
-                debug::warn!("[product_tracking_ocw] notify_listener error: {}", err);
-                break;
-            }
-            last_processed_block = current_block;
+            Ok(())
         }
 
-        // Save last processed block
-        if last_processed_block >= start_block {
-            last_processed_block_ref.set(&last_processed_block);
-            debug::info!(
-                "[product_tracking_ocw] Notifications successfully processed up to block {}",
-                last_processed_block
-            );
-        }
-    }
+        #[weight = 10_000]
+        pub fn accept_custody(origin, id: ShipmentId) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
 
-    fn notify_listener(ev: &ShippingEvent<T::Moment>) -> Result<(), &'static str> {
-        debug::info!("notifying listener: {:?}", ev);
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
 
-        let request =
-            sp_runtime::offchain::http::Request::post(&LISTENER_ENDPOINT, vec![ev.to_string()]);
+            // Check shipment is known & can still change hands (1 DB read)
+            Self::validate_active_shipment(&id)?;
 
-        let timeout =
-            sp_io::offchain::timestamp().add(sp_runtime::offchain::Duration::from_millis(3000));
+            // Check the handover was offered to the sender (1 DB read)
+            let receiver = <PendingHandovers<T>>::get(&id).ok_or(<Error<T>>::NoPendingHandover)?;
+            ensure!(receiver == who, <Error<T>>::SenderIsNotHandoverReceiver);
+            let previous = <Custodians<T>>::get(&id).ok_or(<Error<T>>::SenderIsNotCustodian)?;
 
-        let pending = request
-            .add_header(&"Content-Type", &"text/plain")
-            .deadline(timeout) // Setting the timeout time
-            .send() // Sending the request out by the host
-            .map_err(|_| "http post request building error")?;
+            // Create shipping event
+            let event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentHandoverAcceptance)
+                .for_shipment(id.clone())
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(<timestamp::Module<T>>::now())
+                .build();
 
-        let response = pending
-            .try_wait(timeout)
-            .map_err(|_| "http post request sent error")?
-            .map_err(|_| "http post request sent error")?;
+            // Storage writes
+            // --------------
+            // Store shipping event (2 DB reads, 5 DB writes)
+            let event_idx = Self::store_event(event)?;
+            // Transfer custody (3 DB writes)
+            <PendingHandovers<T>>::remove(&id);
+            Self::set_custodian(&id, who.clone(), event_idx);
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
 
-        if response.code != 200 {
-            return Err("http response error");
+            // Raise events
+            Self::deposit_event(RawEvent::CustodyTransferred(previous, id, who));
+
+            Ok(())
         }
 
-        Ok(())
+        #[weight = 10_000]
+        pub fn confirm_delivery(origin, id: ShipmentId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Check shipment is known (1 DB read) & is awaiting the sender's confirmation,
+            // or was disputed by the sender & not returned since
+            let shipment = match <Shipments<T>>::get(&id) {
+                Some(shipment) => match shipment.status {
+                    _ if shipment.consignee != who => Err(<Error<T>>::SenderIsNotConsignee),
+                    _ if shipment.returned_as.is_some() => Err(<Error<T>>::ShipmentHasBeenReturned),
+                    ShipmentStatus::AwaitingConfirmation | ShipmentStatus::Disputed => Ok(shipment),
+                    ShipmentStatus::Delivered => Err(<Error<T>>::ShipmentHasBeenDelivered),
+                    _ => Err(<Error<T>>::ShipmentIsNotAwaitingConfirmation),
+                }
+                None => Err(<Error<T>>::ShipmentIsUnknown)
+            }?;
+
+            // Confirm delivery (2 DB reads, 5 DB writes)
+            let event_idx = Self::do_confirm_delivery(shipment)?;
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeliveryConfirmed(who.clone(), id.clone(), event_idx));
+            Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, ShipmentStatus::Delivered));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn dispute_delivery(origin, id: ShipmentId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Check shipment is known (1 DB read) & is awaiting the sender's confirmation
+            let shipment = match <Shipments<T>>::get(&id) {
+                Some(shipment) => match shipment.status {
+                    _ if shipment.consignee != who => Err(<Error<T>>::SenderIsNotConsignee),
+                    ShipmentStatus::AwaitingConfirmation => Ok(shipment),
+                    ShipmentStatus::Delivered => Err(<Error<T>>::ShipmentHasBeenDelivered),
+                    ShipmentStatus::Disputed => Err(<Error<T>>::ShipmentIsDisputed),
+                    _ => Err(<Error<T>>::ShipmentIsNotAwaitingConfirmation),
+                }
+                None => Err(<Error<T>>::ShipmentIsUnknown)
+            }?;
+
+            // Update shipment status, so that the delivery isn't confirmed automatically
+            let shipment = shipment.dispute_delivery();
+            let status = shipment.status.clone();
+
+            // Create shipping event
+            let event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentDeliveryDispute)
+                .for_shipment(id.clone())
+                .at_location(None)
+                .with_readings(vec![])
+                .at_time(<timestamp::Module<T>>::now())
+                .build();
+
+            // Storage writes
+            // --------------
+            // Store shipping event (2 DB reads, 5 DB writes)
+            let event_idx = Self::store_event(event)?;
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+            // Update shipment (1 DB write)
+            <Shipments<T>>::insert(&id, shipment);
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeliveryDisputed(who.clone(), id.clone(), event_idx));
+            Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, status));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn recall(origin, product_id: ProductId, lot: Option<LotId>) -> dispatch::DispatchResult {
+            T::RecallOrigin::ensure_origin(origin)?;
+
+            // Validate format of lot ID
+            if let Some(lot) = &lot {
+                Self::validate_lot_identifier(lot)?;
+            }
+
+            // Check product (or lot) isn't recalled yet (1 DB read)
+            let key = (product_id.clone(), lot.clone());
+            ensure!(!<Recalls<T>>::contains_key(&key), <Error<T>>::RecallAlreadyExists);
+
+            // Record recall (1 DB write)
+            <Recalls<T>>::insert(&key, <timestamp::Module<T>>::now());
+
+            // Flag all shipments carrying the recalled product (or lot) (1 DB read, then
+            // 1 DB read, 5 DB writes per shipment)
+            let shipments = Self::shipments_affected_by_recall(&product_id, &lot);
+            for id in shipments.iter() {
+                if let Some(mut shipment) = <Shipments<T>>::get(id) {
+                    Self::store_recall_event(id, ShippingEventType::ShipmentRecall)?;
+                    shipment.recalled = true;
+                    <Shipments<T>>::insert(id, shipment);
+                }
+            }
+
+            // Raise events
+            Self::deposit_event(RawEvent::ProductRecalled(product_id, lot, shipments));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn clear_recall(origin, product_id: ProductId, lot: Option<LotId>) -> dispatch::DispatchResult {
+            T::RecallOrigin::ensure_origin(origin)?;
+
+            // Validate format of lot ID
+            if let Some(lot) = &lot {
+                Self::validate_lot_identifier(lot)?;
+            }
+
+            // Check product (or lot) is recalled (1 DB read)
+            let key = (product_id.clone(), lot.clone());
+            ensure!(<Recalls<T>>::contains_key(&key), <Error<T>>::RecallIsUnknown);
+
+            // Remove recall (1 DB write)
+            <Recalls<T>>::remove(&key);
+
+            // Clear flag of shipments that aren't affected by another recall (1 DB read, then
+            // 1 DB read & 1 DB read per product & lot, 5 DB writes per cleared shipment)
+            let mut cleared = vec![];
+            for id in Self::shipments_affected_by_recall(&product_id, &lot) {
+                if let Some(mut shipment) = <Shipments<T>>::get(&id) {
+                    if shipment.recalled && !Self::is_recalled(&shipment.products) {
+                        Self::store_recall_event(&id, ShippingEventType::ShipmentRecallClearance)?;
+                        shipment.recalled = false;
+                        <Shipments<T>>::insert(&id, shipment);
+                        cleared.push(id);
+                    }
+                }
+            }
+
+            // Raise events
+            Self::deposit_event(RawEvent::RecallCleared(product_id, lot, cleared));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn set_shipment_rules(origin, id: ShipmentId, rules: Vec<ThresholdRule>) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Validate threshold rules
+            Self::validate_threshold_rules(&rules)?;
+
+            // Check shipment is known & hasn't been delivered yet (1 DB read)
+            let shipment = Self::validate_active_shipment(&id)?;
+
+            // Only the shipment's owner or current custodian can set its rules (1 DB read)
+            Self::validate_owner_or_custodian(&shipment, &who)?;
+
+            // Update shipment rules (1 DB write)
+            <ShipmentRules>::insert(&id, rules);
+
+            // Raise events
+            Self::deposit_event(RawEvent::ShipmentRulesUpdated(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn set_shipment_route(origin, id: ShipmentId, route: Option<Route>) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Validate planned route
+            if let Some(route) = &route {
+                Self::validate_route(route)?;
+            }
+
+            // Check shipment is known & hasn't been delivered yet (1 DB read)
+            let shipment = Self::validate_active_shipment(&id)?;
+
+            // Only the shipment's owner or current custodian can set its route (1 DB read)
+            Self::validate_owner_or_custodian(&shipment, &who)?;
+
+            // Update shipment route (1 DB write)
+            match route {
+                Some(route) => <ShipmentRoutes>::insert(&id, route),
+                None => <ShipmentRoutes>::remove(&id),
+            }
+
+            // Raise events
+            Self::deposit_event(RawEvent::ShipmentRouteUpdated(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn set_transport_mode(origin, id: ShipmentId, mode: TransportMode) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Check shipment is known & hasn't been delivered yet (1 DB read)
+            let mut shipment = Self::validate_active_shipment(&id)?;
+
+            // Only the shipment's owner or current custodian can set its transport mode (1 DB read)
+            Self::validate_owner_or_custodian(&shipment, &who)?;
+            shipment.transport_mode = Some(mode.clone());
+
+            // Update shipment (1 DB write)
+            <Shipments<T>>::insert(&id, shipment);
+
+            // Raise events
+            Self::deposit_event(RawEvent::ShipmentTransportModeUpdated(who, id, mode));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn set_product_rules(origin, product_id: ProductId, rules: Vec<ThresholdRule>) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate threshold rules
+            Self::validate_threshold_rules(&rules)?;
+
+            // Check product is known & belongs to the sender's organization (1 DB read)
+            match <product_registry::Module<T>>::owner_of(&product_id) {
+                Some(product_owner) => ensure!(
+                    product_owner == who,
+                    <Error<T>>::ProductIsNotOwnedByOrganization
+                ),
+                None => return Err(<Error<T>>::ProductIsUnknown.into()),
+            }
+
+            // Update product rules (1 DB write)
+            <ProductRules>::insert(&product_id, rules);
+
+            // Raise events
+            Self::deposit_event(RawEvent::ProductRulesUpdated(who, product_id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_device(
+            origin,
+            id: DeviceId,
+            owner: T::AccountId,
+            reading_types: Vec<ReadingType>,
+            public_key: Option<DevicePublicKey>
+        ) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of device ID
+            Self::validate_device_identifier(&id)?;
+
+            // Validate supported reading types (1 DB read per custom reading type)
+            ensure!(
+                reading_types.len() <= DEVICE_MAX_READING_TYPES,
+                <Error<T>>::DeviceHasTooManyReadingTypes
+            );
+            for reading_type in reading_types.iter() {
+                Self::dimension_of(reading_type).ok_or(<Error<T>>::ReadingTypeIsUnknown)?;
+            }
+
+            // Check device doesn't exist yet (1 DB read)
+            ensure!(!<Devices<T>>::contains_key(&id), <Error<T>>::DeviceAlreadyExists);
+
+            // Create a device instance
+            let device = Self::new_device()
+                .identified_by(id.clone())
+                .owned_by(owner.clone())
+                .supporting(reading_types)
+                .with_public_key(public_key)
+                .registered_at(<timestamp::Module<T>>::now())
+                .build();
+
+            // Storage writes
+            // --------------
+            // Add device (2 DB writes)
+            <Devices<T>>::insert(&id, device);
+            <DevicesOfOrganization<T>>::append(&owner, &id);
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeviceRegistered(who, id, owner));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn decommission_device(origin, id: DeviceId) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of device ID
+            Self::validate_device_identifier(&id)?;
+
+            // Check device is known & active (1 DB read)
+            let device = <Devices<T>>::get(&id).ok_or(<Error<T>>::DeviceIsUnknown)?;
+            ensure!(device.status == DeviceStatus::Active, <Error<T>>::DeviceIsDecommissioned);
+
+            // Only the device's owner can decommission it
+            ensure!(device.owner == who, <Error<T>>::SenderIsNotDeviceOwner);
+
+            // Update device (1 DB write)
+            <Devices<T>>::insert(&id, device.decommission());
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeviceDecommissioned(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn attach_device(origin, shipment_id: ShipmentId, device_id: DeviceId) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment & device IDs
+            Self::validate_identifier(&shipment_id)?;
+            Self::validate_device_identifier(&device_id)?;
+
+            // Check shipment is known & hasn't been delivered yet (1 DB read)
+            Self::validate_active_shipment(&shipment_id)?;
+
+            // Only the current custodian can attach a device to the shipment (1 DB read)
+            Self::validate_custodian(&shipment_id, &who)?;
+
+            // Check device is known & active (1 DB read)
+            let device = <Devices<T>>::get(&device_id).ok_or(<Error<T>>::DeviceIsUnknown)?;
+            ensure!(device.status == DeviceStatus::Active, <Error<T>>::DeviceIsDecommissioned);
+
+            // Check device can still be attached to the shipment (1 DB read)
+            let devices = <DevicesOfShipment>::get(&shipment_id);
+            ensure!(!devices.contains(&device_id), <Error<T>>::DeviceIsAlreadyAttached);
+            ensure!(devices.len() < SHIPMENT_MAX_DEVICES, <Error<T>>::ShipmentHasTooManyDevices);
+
+            // Attach device (1 DB write)
+            <DevicesOfShipment>::append(&shipment_id, &device_id);
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeviceAttached(who, shipment_id, device_id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn record_calibration(origin, device_id: DeviceId, calibration: Calibration<T::Moment>) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of device ID
+            Self::validate_device_identifier(&device_id)?;
+
+            // Check device is known (1 DB read)
+            let device = <Devices<T>>::get(&device_id).ok_or(<Error<T>>::DeviceIsUnknown)?;
+
+            // Only the device's owner can record its calibrations
+            ensure!(device.owner == who, <Error<T>>::SenderIsNotDeviceOwner);
+
+            // Validate calibration against the device's previous ones (1 DB read)
+            let calibrations = <Calibrations<T>>::get(&device_id);
+            Self::validate_calibration(&calibrations, &calibration)?;
+            let index = calibrations.len() as u32;
+
+            // Add calibration (1 DB write)
+            <Calibrations<T>>::append(&device_id, calibration);
+
+            // Raise events
+            Self::deposit_event(RawEvent::DeviceCalibrated(who, device_id, index));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn anchor_readings(
+            origin,
+            id: ShipmentId,
+            root: H256,
+            count: u32,
+            #[compact] from: T::Moment,
+            #[compact] to: T::Moment
+        ) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate format of shipment ID
+            Self::validate_identifier(&id)?;
+
+            // Validate batch of readings
+            ensure!(count > 0 && from <= to, <Error<T>>::InvalidReadingBatch);
+
+            // Check shipment is known & hasn't been delivered yet (1 DB read)
+            Self::validate_active_shipment(&id)?;
+
+            // Only the current custodian can anchor readings to the shipment (1 DB read)
+            Self::validate_custodian(&id, &who)?;
+
+            // Anchor batch (1 DB read, 1 DB write)
+            let batch_idx = <BatchesOfShipment<T>>::decode_len(&id).unwrap_or(0) as u32;
+            <BatchesOfShipment<T>>::append(&id, ReadingBatch { root, count, from, to });
+
+            // Raise events
+            Self::deposit_event(RawEvent::ReadingsAnchored(who, id, batch_idx, root));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn set_plausibility_bounds(origin, reading_type: ReadingType, bounds: Option<ReadingBounds>) -> dispatch::DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            // Check reading type is known (1 DB read for a custom reading type)
+            Self::dimension_of(&reading_type).ok_or(<Error<T>>::ReadingTypeIsUnknown)?;
+
+            // Validate bounds
+            if let Some(bounds) = &bounds {
+                ensure!(bounds.min <= bounds.max, <Error<T>>::InvalidReadingBounds);
+            }
+
+            // Update or remove bounds (1 DB write)
+            match &bounds {
+                Some(bounds) => <PlausibilityBounds>::insert(&reading_type, bounds),
+                None => <PlausibilityBounds>::remove(&reading_type),
+            }
+
+            // Raise events
+            Self::deposit_event(RawEvent::PlausibilityBoundsUpdated(reading_type, bounds));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_reading_type(origin, id: ReadingTypeId, reading_type: CustomReadingType) -> dispatch::DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            // Validate reading type's name
+            ensure!(
+                !reading_type.name.is_empty() && reading_type.name.len() <= READING_TYPE_NAME_MAX_LENGTH,
+                <Error<T>>::InvalidOrMissingIdentifier
+            );
+
+            // Check reading type doesn't exist yet (1 DB read)
+            ensure!(!<CustomReadingTypes>::contains_key(id), <Error<T>>::ReadingTypeAlreadyExists);
+            let dimension = reading_type.dimension.clone();
+
+            // Add reading type (1 DB write)
+            <CustomReadingTypes>::insert(id, reading_type);
+
+            // Raise events
+            Self::deposit_event(RawEvent::ReadingTypeRegistered(id, dimension));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_facility(
+            origin,
+            id: FacilityId,
+            owner: T::AccountId,
+            address_hash: H256,
+            geofence: Geofence
+        ) -> dispatch::DispatchResult {
+            <T as Trait>::CreateRoleOrigin::ensure_origin(origin.clone())?;
+            let who = ensure_signed(origin)?;
+
+            // Validate facility's GLN & geofence
+            Self::validate_facility_identifier(&id)?;
+            Self::validate_geofence(&geofence)?;
+
+            // Check facility doesn't exist yet (1 DB read)
+            ensure!(!<Facilities<T>>::contains_key(&id), <Error<T>>::FacilityAlreadyExists);
+
+            // Geofence must overlap few enough grid cells, each of which must have room for
+            // the facility (1 DB read per cell)
+            let cells = geofence.cells(FACILITY_MAX_CELLS).ok_or(Error::<T>::GeofenceIsTooLarge)?;
+            for cell in &cells {
+                ensure!(
+                    <FacilitiesOfCell>::decode_len(cell).unwrap_or(0) < CELL_MAX_FACILITIES,
+                    Error::<T>::AreaHasTooManyFacilities
+                );
+            }
+
+            // Create a facility instance
+            let facility = Self::new_facility()
+                .identified_by(id.clone())
+                .owned_by(owner.clone())
+                .located_at(address_hash)
+                .within(geofence)
+                .registered_at(<timestamp::Module<T>>::now())
+                .build();
+
+            // Storage writes
+            // --------------
+            // Add facility (2 DB writes + 1 DB write per cell)
+            <Facilities<T>>::insert(&id, facility);
+            <FacilitiesOfOrganization<T>>::append(&owner, &id);
+            for cell in cells {
+                <FacilitiesOfCell>::append(cell, &id);
+            }
+
+            // Raise events
+            Self::deposit_event(RawEvent::FacilityRegistered(who, id, owner));
+
+            Ok(())
+        }
+
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            // Deliveries that haven't been confirmed in time are considered as delivered
+            let deadlines = <DeliveryDeadlines<T>>::take(block_number);
+            let mut weight = T::DbWeight::get().reads_writes(1, 1);
+
+            for id in deadlines {
+                weight = weight.saturating_add(T::DbWeight::get().reads(1));
+                let shipment = match <Shipments<T>>::get(&id) {
+                    Some(shipment) if shipment.status == ShipmentStatus::AwaitingConfirmation => shipment,
+                    _ => continue,
+                };
+                weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 5));
+                match Self::do_confirm_delivery(shipment) {
+                    Ok(event_idx) => Self::deposit_event(RawEvent::DeliveryConfirmationTimedOut(id, event_idx)),
+                    Err(err) => debug::warn!("[product_tracking] delivery confirmation error: {:?}", err),
+                }
+            }
+
+            // Shipments on the move that haven't received any event since are inactive
+            let deadlines = <IdleDeadlines<T>>::take(block_number);
+            weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+
+            for id in deadlines {
+                weight = weight.saturating_add(T::DbWeight::get().reads(2));
+                let last_activity = match <LastActivity<T>>::get(&id) {
+                    Some(last_activity) if last_activity + T::IdleTimeout::get() == block_number => last_activity,
+                    _ => continue,
+                };
+                match <Shipments<T>>::get(&id).map(|shipment| shipment.status) {
+                    Some(ShipmentStatus::InTransit) | Some(ShipmentStatus::Delayed) | Some(ShipmentStatus::Damaged) => {
+                        Self::deposit_event(RawEvent::ShipmentInactive(id, last_activity))
+                    }
+                    _ => (),
+                }
+            }
+
+            weight
+        }
+
+        fn offchain_worker(block_number: T::BlockNumber) {
+            // Acquiring the lock
+            let mut lock = StorageLock::<Time>::with_deadline(
+                b"product_tracking_ocw::lock",
+                rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION)
+            );
+
+            match lock.try_lock() {
+                Ok(_guard) => { Self::process_ocw_notifications(block_number); }
+                Err(_err) => { debug::info!("[product_tracking_ocw] lock is already acquired"); }
+            };
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    // Helper methods
+    fn new_shipment() -> ShipmentBuilder<T::AccountId, T::Moment> {
+        ShipmentBuilder::<T::AccountId, T::Moment>::default()
+    }
+
+    fn new_shipping_event() -> ShippingEventBuilder<T::Moment> {
+        ShippingEventBuilder::<T::Moment>::default()
+    }
+
+    fn new_device() -> DeviceBuilder<T::AccountId, T::Moment> {
+        DeviceBuilder::<T::AccountId, T::Moment>::default()
+    }
+
+    fn new_facility() -> FacilityBuilder<T::AccountId, T::Moment> {
+        FacilityBuilder::<T::AccountId, T::Moment>::default()
+    }
+
+    fn do_track_shipment(
+        who: T::AccountId,
+        shipment: Shipment<T::AccountId, T::Moment>,
+        operation: ShippingOperation,
+        input: ShippingEventInput<T::Moment>,
+    ) -> Result<ShippingEventIndex, Error<T>> {
+        let id = shipment.id.clone();
+        let ShippingEventInput {
+            timestamp,
+            location,
+            readings,
+            delivery_note,
+            facility,
+        } = input;
+
+        // Coordinates must be in range
+        if let Some(location) = &location {
+            ensure!(location.is_valid(), Error::<T>::InvalidLocation);
+        }
+
+        // Event takes place at the given facility, or at the one whose geofence contains its
+        // location (1 DB read, or 1 DB read + 1 DB read per facility of the location's cell)
+        let facility = Self::resolve_facility(facility, &location)?;
+
+        // Readings must be plausible (up to 2 DB reads per reading)
+        Self::validate_readings_plausibility(&shipment, &readings, timestamp).map_err(
+            |(idx, err)| {
+                debug::warn!(
+                    "[product_tracking] reading #{} of shipment {:?} is invalid: {}",
+                    idx,
+                    id,
+                    err.as_str()
+                );
+                err
+            },
+        )?;
+
+        // Readings must come from active devices attached to the shipment, signed readings
+        // must be verified, & readings are corrected (1 DB read + 2 DB reads per reading)
+        let annotations = Self::validate_readings(&id, &readings)?;
+
+        let previous_status = shipment.status.clone();
+
+        // Do transition checks & update shipment status
+        let mut shipment = Self::apply_operation(shipment, &operation, timestamp, delivery_note)?;
+        let status = shipment.status.clone();
+
+        // Monitoring relies on the corrected values of the readings, so that they're comparable
+        let corrected = readings
+            .iter()
+            .zip(annotations.iter())
+            .map(|(reading, annotation)| reading.corrected(annotation))
+            .collect::<Vec<_>>();
+
+        // Check readings against the shipment's threshold rules (1 DB read + 1 DB read per product)
+        let breaches = Self::detect_excursions(&shipment, &corrected);
+        if !breaches.is_empty() {
+            shipment.compliant = false;
+        }
+
+        // Check location against the shipment's planned route, if any (1 DB read)
+        let deviation = location
+            .as_ref()
+            .and_then(|location| Self::detect_deviation(&id, &location.position()));
+        if deviation.is_some() {
+            shipment.deviations = shipment.deviations.saturating_add(1);
+        }
+
+        // Update running aggregates of the readings (1 DB read per reading type)
+        let aggregates = Self::aggregate_readings(&id, &corrected);
+
+        // Detect geofence crossings & accumulate the distance travelled since the shipment's
+        // last known position (1 DB read)
+        let position = location.as_ref().map(|location| LastPosition {
+            point: location.position(),
+            facility: facility.clone(),
+            timestamp,
+        });
+        let previous = position.as_ref().and_then(|_| <LastPositions<T>>::get(&id));
+        let crossings = match &position {
+            Some(position) => Self::detect_crossings(&previous, position),
+            None => vec![],
+        };
+        let travelled = match (&previous, &position) {
+            (Some(previous), Some(position)) => {
+                geo::distance(&previous.point, &position.point).unwrap_or_default()
+            }
+            _ => 0,
+        };
+        shipment.distance = shipment.distance.saturating_add(travelled.into());
+
+        // Track how long the shipment has been scanned around the same place (1 DB read)
+        let dwell = match (&operation, &position) {
+            (ShippingOperation::Scan, Some(position)) => {
+                Some(Self::track_dwell(&id, &position.point, timestamp))
+            }
+            _ => None,
+        };
+
+        // Estimate the emissions of the whole trip upon delivery
+        if operation == ShippingOperation::Deliver {
+            shipment.emissions = shipment
+                .transport_mode
+                .as_ref()
+                .map(|mode| T::EmissionFactors::get().emissions(mode, shipment.distance));
+        }
+
+        // Create shipping event
+        let event = Self::new_shipping_event()
+            .of_type(operation.clone().into())
+            .for_shipment(id.clone())
+            .at_location(location.clone())
+            .at_facility(facility)
+            .with_readings(readings)
+            .with_annotations(annotations)
+            .at_time(timestamp)
+            .build();
+
+        // Storage writes
+        // --------------
+        // Store shipping event (2 DB reads, 5 DB writes)
+        let event_idx = Self::store_event(event)?;
+        // Update offchain notifications (1 DB write)
+        <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+        // Update reading aggregates (1 DB write per reading type)
+        for (reading_type, aggregate) in aggregates {
+            <ReadingAggregates<T>>::insert(&id, reading_type, aggregate);
+        }
+
+        if let Some(distance) = deviation {
+            Self::deposit_event(RawEvent::RouteDeviation(id.clone(), event_idx, distance));
+        }
+
+        if let Some((dwell, idle)) = dwell {
+            // Update shipment's dwell (1 DB write)
+            <Dwells<T>>::insert(&id, dwell);
+            if let Some(duration) = idle {
+                Self::deposit_event(RawEvent::ShipmentIdle(id.clone(), duration));
+            }
+        } else if operation == ShippingOperation::Deliver {
+            // Shipment's journey is over (1 DB write)
+            <Dwells<T>>::remove(&id);
+        }
+
+        if let Some(position) = position {
+            // Update shipment's last known position (1 DB write)
+            <LastPositions<T>>::insert(&id, position);
+        }
+
+        // Store derived arrival & departure events (2 DB reads, 6 DB writes per crossing)
+        for (event_type, crossed) in crossings {
+            let derived = Self::new_shipping_event()
+                .of_type(event_type.clone())
+                .for_shipment(id.clone())
+                .at_location(location.clone())
+                .at_facility(Some(crossed.clone()))
+                .at_time(timestamp)
+                .build();
+            let derived_idx = Self::store_event(derived)?;
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), derived_idx);
+            match event_type {
+                ShippingEventType::ShipmentArrival => {
+                    Self::deposit_event(RawEvent::ShipmentArrived(id.clone(), crossed, derived_idx))
+                }
+                _ => Self::deposit_event(RawEvent::ShipmentDeparted(
+                    id.clone(),
+                    crossed,
+                    derived_idx,
+                )),
+            }
+        }
+
+        if operation == ShippingOperation::Deliver {
+            // Consignee must confirm the delivery before the deadline (1 DB write)
+            let deadline =
+                <system::Module<T>>::block_number() + T::DeliveryConfirmationTimeout::get();
+            <DeliveryDeadlines<T>>::append(deadline, &id);
+        }
+
+        let status_updated = operation != ShippingOperation::Scan || status != previous_status;
+        if status_updated || !breaches.is_empty() || deviation.is_some() || travelled > 0 {
+            // Update shipment (1 DB write)
+            <Shipments<T>>::insert(&id, &shipment);
+        }
+
+        if !breaches.is_empty() {
+            // Log excursions (1 DB write per excursion)
+            let excursions = breaches.into_iter().map(|reading| Excursion {
+                event_idx,
+                device_id: reading.device_id,
+                reading_type: reading.reading_type,
+                value: reading.value,
+                timestamp: reading.timestamp,
+            });
+            for excursion in excursions {
+                Self::deposit_event(RawEvent::ExcursionDetected(
+                    id.clone(),
+                    event_idx,
+                    excursion.reading_type.clone(),
+                    excursion.value,
+                ));
+                <ExcursionsOfShipment<T>>::append(&id, excursion);
+            }
+        }
+
+        if operation == ShippingOperation::Deliver {
+            // Issue compliance certificate (2 DB reads + 1 DB read per event, 1 DB write)
+            let certificate = Self::issue_certificate(&shipment, timestamp);
+            let compliant = certificate.compliant;
+            <Certificates<T>>::insert(&id, certificate);
+            Self::deposit_event(RawEvent::ComplianceCertificateIssued(id.clone(), compliant));
+
+            if let (Some(mode), Some(emissions)) = (&shipment.transport_mode, shipment.emissions) {
+                Self::deposit_event(RawEvent::EmissionsEstimated(
+                    id.clone(),
+                    mode.clone(),
+                    emissions,
+                ));
+            }
+        }
+
+        if status_updated {
+            // Raise events
+            match operation {
+                ShippingOperation::ReportDamage => Self::deposit_event(RawEvent::ShipmentDamaged(
+                    who.clone(),
+                    id.clone(),
+                    event_idx,
+                )),
+                ShippingOperation::ReportLoss => {
+                    Self::deposit_event(RawEvent::ShipmentLost(who.clone(), id.clone(), event_idx))
+                }
+                ShippingOperation::ReportDelay => Self::deposit_event(RawEvent::ShipmentDelayed(
+                    who.clone(),
+                    id.clone(),
+                    event_idx,
+                )),
+                _ => (),
+            };
+            Self::deposit_event(RawEvent::ShipmentStatusUpdated(who, id, event_idx, status));
+        }
+
+        Ok(event_idx)
+    }
+
+    fn apply_operation(
+        shipment: Shipment<T::AccountId, T::Moment>,
+        operation: &ShippingOperation,
+        timestamp: T::Moment,
+        delivery_note: Option<DeliveryNote>,
+    ) -> Result<Shipment<T::AccountId, T::Moment>, Error<T>> {
+        match (shipment.status.clone(), operation) {
+            (ShipmentStatus::Delivered, _) => Err(Error::<T>::ShipmentHasBeenDelivered),
+            (ShipmentStatus::Cancelled, _) => Err(Error::<T>::ShipmentHasBeenCancelled),
+            (ShipmentStatus::AwaitingConfirmation, _) => {
+                Err(Error::<T>::ShipmentIsAwaitingConfirmation)
+            }
+            (ShipmentStatus::Disputed, _) => Err(Error::<T>::ShipmentIsDisputed),
+            // A lost shipment can only be found again by scanning it
+            (ShipmentStatus::Lost, ShippingOperation::Scan) => Ok(shipment.resume()),
+            (ShipmentStatus::Lost, _) => Err(Error::<T>::ShipmentIsLost),
+            (ShipmentStatus::Pending, ShippingOperation::Pickup) => Ok(shipment.pickup()),
+            (_, ShippingOperation::Pickup) => Err(Error::<T>::ShipmentIsInTransit),
+            // Exceptions can only be reported once the shipment has been picked up
+            (ShipmentStatus::Pending, ShippingOperation::ReportDamage)
+            | (ShipmentStatus::Pending, ShippingOperation::ReportLoss)
+            | (ShipmentStatus::Pending, ShippingOperation::ReportDelay) => {
+                Err(Error::<T>::ShipmentIsPending)
+            }
+            // A delayed shipment that is scanned again is back on track
+            (ShipmentStatus::Delayed, ShippingOperation::Scan) => Ok(shipment.resume()),
+            (_, ShippingOperation::Scan) => Ok(shipment),
+            // A damaged shipment can still be delivered, it remains flagged as damaged
+            (_, ShippingOperation::Deliver) if shipment.recalled => {
+                Err(Error::<T>::ShipmentIsRecalled)
+            }
+            (_, ShippingOperation::Deliver) => {
+                let quantities =
+                    Self::validate_delivery_note(&shipment.products, delivery_note, timestamp)?;
+                Ok(shipment.deliver(timestamp, quantities))
+            }
+            (_, ShippingOperation::ReportDamage) => Ok(shipment.report_damage()),
+            (_, ShippingOperation::ReportLoss) => Ok(shipment.report_loss()),
+            (_, ShippingOperation::ReportDelay) => Ok(shipment.report_delay()),
+        }
+    }
+
+    fn store_event(event: ShippingEvent<T::Moment>) -> Result<ShippingEventIndex, Error<T>> {
+        let event_idx = EventCount::get()
+            .checked_add(1)
+            .ok_or(Error::<T>::ShippingEventMaxExceeded)?;
+
+        EventCount::put(event_idx);
+        EventsOfShipment::append(&event.shipment_id, event_idx);
+
+        // Shipment becomes inactive if it doesn't receive another event before the deadline
+        let now = <system::Module<T>>::block_number();
+        if <LastActivity<T>>::get(&event.shipment_id) != Some(now) {
+            <LastActivity<T>>::insert(&event.shipment_id, now);
+            <IdleDeadlines<T>>::append(now + T::IdleTimeout::get(), &event.shipment_id);
+        }
+
+        <AllEvents<T>>::insert(event_idx, event);
+
+        Ok(event_idx)
+    }
+
+    fn do_confirm_delivery(
+        shipment: Shipment<T::AccountId, T::Moment>,
+    ) -> Result<ShippingEventIndex, Error<T>> {
+        let id = shipment.id.clone();
+        let shipment = shipment.confirm_delivery();
+
+        // Create shipping event
+        let event = Self::new_shipping_event()
+            .of_type(ShippingEventType::ShipmentDeliveryConfirmation)
+            .for_shipment(id.clone())
+            .at_location(None)
+            .with_readings(vec![])
+            .at_time(<timestamp::Module<T>>::now())
+            .build();
+
+        // Storage writes
+        // --------------
+        // Store shipping event (2 DB reads, 5 DB writes)
+        let event_idx = Self::store_event(event)?;
+        // Update offchain notifications (1 DB write)
+        <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+        // Update shipment (1 DB write)
+        <Shipments<T>>::insert(&id, shipment);
+
+        Ok(event_idx)
+    }
+
+    fn index_shipment_products(shipment: &Shipment<T::AccountId, T::Moment>) {
+        for line in shipment.products.iter() {
+            <ShipmentsOfProduct>::mutate(&line.product_id, |shipments| {
+                if !shipments.contains(&shipment.id) {
+                    shipments.push(shipment.id.clone());
+                }
+            });
+            if let Some(lot) = &line.lot {
+                <ShipmentsOfLot>::mutate(&line.product_id, lot, |shipments| {
+                    if !shipments.contains(&shipment.id) {
+                        shipments.push(shipment.id.clone());
+                    }
+                });
+            }
+        }
+    }
+
+    fn is_recalled(props: &[ShipmentLine<T::Moment>]) -> bool {
+        props.iter().any(|line| {
+            <Recalls<T>>::contains_key((line.product_id.clone(), None::<LotId>))
+                || line.lot.as_ref().map_or(false, |lot| {
+                    <Recalls<T>>::contains_key((line.product_id.clone(), Some(lot.clone())))
+                })
+        })
+    }
+
+    fn shipments_affected_by_recall(product_id: &[u8], lot: &Option<LotId>) -> Vec<ShipmentId> {
+        match lot {
+            Some(lot) => <ShipmentsOfLot>::get(product_id, lot),
+            None => <ShipmentsOfProduct>::get(product_id),
+        }
+    }
+
+    fn store_recall_event(
+        id: &[u8],
+        event_type: ShippingEventType,
+    ) -> Result<ShippingEventIndex, Error<T>> {
+        let event = Self::new_shipping_event()
+            .of_type(event_type)
+            .for_shipment(id.to_vec())
+            .at_location(None)
+            .with_readings(vec![])
+            .at_time(<timestamp::Module<T>>::now())
+            .build();
+        let event_idx = Self::store_event(event)?;
+        <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+        Ok(event_idx)
+    }
+
+    fn threshold_rules_of(shipment: &Shipment<T::AccountId, T::Moment>) -> Vec<ThresholdRule> {
+        let mut rules = <ShipmentRules>::get(&shipment.id);
+        let mut products: Vec<&ProductId> = shipment
+            .products
+            .iter()
+            .map(|line| &line.product_id)
+            .collect();
+        products.sort();
+        products.dedup();
+        for product_id in products {
+            rules.extend(<ProductRules>::get(product_id));
+        }
+        rules
+    }
+
+    fn detect_excursions(
+        shipment: &Shipment<T::AccountId, T::Moment>,
+        readings: &[Reading<T::Moment>],
+    ) -> Vec<Reading<T::Moment>> {
+        if readings.is_empty() {
+            return vec![];
+        }
+        let rules = Self::threshold_rules_of(shipment);
+        readings
+            .iter()
+            .filter(|reading| {
+                rules.iter().any(|rule| {
+                    rule.reading_type == reading.reading_type && rule.is_breached_by(reading.value)
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn aggregate_readings(
+        id: &[u8],
+        readings: &[Reading<T::Moment>],
+    ) -> Vec<(ReadingType, ReadingAggregate<T::Moment>)> {
+        let mut aggregates: Vec<(ReadingType, ReadingAggregate<T::Moment>)> = vec![];
+        for reading in readings.iter() {
+            match aggregates
+                .iter()
+                .position(|(reading_type, _)| *reading_type == reading.reading_type)
+            {
+                Some(pos) => {
+                    let aggregate = aggregates[pos].1.clone();
+                    aggregates[pos].1 = aggregate.including(reading);
+                }
+                None => {
+                    let aggregate = match <ReadingAggregates<T>>::get(id, &reading.reading_type) {
+                        Some(aggregate) => aggregate.including(reading),
+                        None => ReadingAggregate::new(reading),
+                    };
+                    aggregates.push((reading.reading_type.clone(), aggregate));
+                }
+            }
+        }
+        aggregates
+    }
+
+    fn issue_certificate(
+        shipment: &Shipment<T::AccountId, T::Moment>,
+        issued: T::Moment,
+    ) -> ComplianceCertificate<T::Moment> {
+        let readings = <EventsOfShipment>::get(&shipment.id)
+            .into_iter()
+            .filter_map(|event_idx| <AllEvents<T>>::get(event_idx))
+            .flat_map(|event| event.corrected_readings())
+            .collect::<Vec<_>>();
+        let rules = Self::threshold_rules_of(shipment);
+        let excursions = <ExcursionsOfShipment<T>>::decode_len(&shipment.id).unwrap_or(0);
+
+        ComplianceCertificate {
+            shipment_id: shipment.id.clone(),
+            compliant: shipment.compliant,
+            summaries: Self::summarize_readings(readings, &rules, issued),
+            excursions: excursions as u32,
+            issued,
+        }
+    }
+
+    fn summarize_readings(
+        mut readings: Vec<Reading<T::Moment>>,
+        rules: &[ThresholdRule],
+        until: T::Moment,
+    ) -> Vec<ReadingSummary<T::Moment>> {
+        readings.sort_by_key(|reading| reading.timestamp);
+
+        let mut reading_types: Vec<ReadingType> = vec![];
+        for reading in readings.iter() {
+            if !reading_types.contains(&reading.reading_type) {
+                reading_types.push(reading.reading_type.clone());
+            }
+        }
+
+        reading_types
+            .into_iter()
+            .map(|reading_type| {
+                let readings = readings
+                    .iter()
+                    .filter(|reading| reading.reading_type == reading_type)
+                    .collect::<Vec<_>>();
+                let is_breached = |value| {
+                    rules
+                        .iter()
+                        .any(|rule| rule.reading_type == reading_type && rule.is_breached_by(value))
+                };
+
+                // Sum in a wider type, so that many readings can't overflow it
+                let mut sum = WideDecimal::from_num(0);
+                let mut min = readings[0].value;
+                let mut max = readings[0].value;
+                let mut time_out_of_range = T::Moment::zero();
+                for (i, reading) in readings.iter().enumerate() {
+                    sum += WideDecimal::from_num(reading.value);
+                    min = min.min(reading.value);
+                    max = max.max(reading.value);
+                    // A breach lasts until the next reading of the same type
+                    if is_breached(reading.value) {
+                        let next = readings.get(i + 1).map_or(until, |next| next.timestamp);
+                        time_out_of_range = time_out_of_range
+                            .saturating_add(next.saturating_sub(reading.timestamp));
+                    }
+                }
+                let count = readings.len();
+
+                ReadingSummary {
+                    reading_type,
+                    count: count as u32,
+                    min,
+                    max,
+                    mean: Decimal::from_num(sum / WideDecimal::from_num(count)),
+                    time_out_of_range,
+                }
+            })
+            .collect()
+    }
+
+    fn dimension_of(reading_type: &ReadingType) -> Option<Dimension> {
+        match reading_type {
+            ReadingType::Custom(id) => <CustomReadingTypes>::get(id).map(|custom| custom.dimension),
+            _ => reading_type.dimension(),
+        }
+    }
+
+    fn resolve_facility(
+        facility: Option<FacilityId>,
+        location: &Option<ReadPoint>,
+    ) -> Result<Option<FacilityId>, Error<T>> {
+        let position = location.as_ref().map(|location| location.position());
+        match (facility, position) {
+            (Some(id), position) => {
+                let facility = <Facilities<T>>::get(&id).ok_or(Error::<T>::FacilityIsUnknown)?;
+                if let Some(position) = position {
+                    ensure!(
+                        facility.geofence.contains(&position),
+                        Error::<T>::LocationIsOutsideFacility
+                    );
+                }
+                Ok(Some(id))
+            }
+            // Of the facilities containing the location, the one with the smallest geofence is
+            // the most specific (e.g. a terminal within a port), ties are broken by GLN
+            (None, Some(position)) => Ok(<FacilitiesOfCell>::get(geo::cell_of(&position))
+                .iter()
+                .filter_map(<Facilities<T>>::get)
+                .filter(|facility| facility.geofence.contains(&position))
+                .min_by_key(|facility| {
+                    (
+                        facility.geofence.area().unwrap_or(u64::max_value()),
+                        facility.id.clone(),
+                    )
+                })
+                .map(|facility| facility.id)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    // Dwell of the shipment, & its duration if it has just exceeded the dwell limit
+    fn track_dwell(
+        id: &[u8],
+        point: &GeoPoint,
+        timestamp: T::Moment,
+    ) -> (Dwell<T::Moment>, Option<T::Moment>) {
+        let dwell = match <Dwells<T>>::get(id) {
+            Some(dwell)
+                if geo::distance(&dwell.point, point)
+                    .map_or(false, |distance| distance <= T::DwellRadius::get()) =>
+            {
+                dwell
+            }
+            _ => Dwell {
+                point: point.clone(),
+                since: timestamp,
+                reported: false,
+            },
+        };
+
+        let duration = timestamp.saturating_sub(dwell.since);
+        if dwell.reported || duration <= T::DwellLimit::get() {
+            return (dwell, None);
+        }
+        (
+            Dwell {
+                reported: true,
+                ..dwell
+            },
+            Some(duration),
+        )
+    }
+
+    fn detect_deviation(id: &[u8], point: &GeoPoint) -> Option<u32> {
+        <ShipmentRoutes>::get(id).and_then(|route| route.deviation(point))
+    }
+
+    // Departure from the facility the shipment was last seen at, & arrival at its new one
+    fn detect_crossings(
+        previous: &Option<LastPosition<T::Moment>>,
+        position: &LastPosition<T::Moment>,
+    ) -> Vec<(ShippingEventType, FacilityId)> {
+        let previous = previous.as_ref().and_then(|last| last.facility.clone());
+        if previous == position.facility {
+            return vec![];
+        }
+
+        let departure = previous.map(|facility| (ShippingEventType::ShipmentDeparture, facility));
+        let arrival = position
+            .facility
+            .clone()
+            .map(|facility| (ShippingEventType::ShipmentArrival, facility));
+        departure.into_iter().chain(arrival).collect()
+    }
+
+    fn set_custodian(id: &[u8], custodian: T::AccountId, event_idx: ShippingEventIndex) {
+        <Custodians<T>>::insert(id, &custodian);
+        <CustodyHistory<T>>::append(id, (custodian, event_idx));
+    }
+
+    // (Public) Validation methods
+    pub fn validate_identifier(id: &[u8]) -> Result<(), Error<T>> {
+        // Basic identifier validation
+        ensure!(!id.is_empty(), Error::<T>::InvalidOrMissingIdentifier);
+        ensure!(
+            id.len() <= IDENTIFIER_MAX_LENGTH,
+            Error::<T>::InvalidOrMissingIdentifier
+        );
+        Ok(())
+    }
+
+    pub fn validate_lot_identifier(id: &[u8]) -> Result<(), Error<T>> {
+        // Lot numbers are up to 20 characters long (GS1 AI 10)
+        ensure!(!id.is_empty(), Error::<T>::InvalidOrMissingIdentifier);
+        ensure!(
+            id.len() <= LOT_ID_MAX_LENGTH,
+            Error::<T>::InvalidOrMissingIdentifier
+        );
+        Ok(())
+    }
+
+    pub fn validate_device_identifier(id: &[u8]) -> Result<(), Error<T>> {
+        ensure!(!id.is_empty(), Error::<T>::InvalidOrMissingIdentifier);
+        ensure!(
+            id.len() <= DEVICE_ID_MAX_LENGTH,
+            Error::<T>::InvalidOrMissingIdentifier
+        );
+        Ok(())
+    }
+
+    pub fn validate_facility_identifier(id: &[u8]) -> Result<(), Error<T>> {
+        // A GLN is made of 13 digits, the last of which is a GS1 check digit
+        ensure!(
+            id.len() == FACILITY_ID_LENGTH && id.iter().all(u8::is_ascii_digit),
+            Error::<T>::InvalidOrMissingIdentifier
+        );
+        let sum: u32 = id[..FACILITY_ID_LENGTH - 1]
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, digit)| u32::from(digit - b'0') * if i % 2 == 0 { 3 } else { 1 })
+            .sum();
+        ensure!(
+            u32::from(id[FACILITY_ID_LENGTH - 1] - b'0') == (10 - sum % 10) % 10,
+            Error::<T>::InvalidOrMissingIdentifier
+        );
+        Ok(())
+    }
+
+    pub fn validate_geofence(geofence: &Geofence) -> Result<(), Error<T>> {
+        ensure!(geofence.is_valid(), Error::<T>::InvalidGeofence);
+        if let Geofence::Polygon(vertices) = geofence {
+            ensure!(
+                vertices.len() <= FACILITY_MAX_GEOFENCE_VERTICES,
+                Error::<T>::InvalidGeofence
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_route(route: &Route) -> Result<(), Error<T>> {
+        ensure!(route.is_valid(), Error::<T>::InvalidRoute);
+        ensure!(
+            route.waypoints.len() <= ROUTE_MAX_WAYPOINTS,
+            Error::<T>::InvalidRoute
+        );
+        Ok(())
+    }
+
+    pub fn validate_new_shipment(id: &[u8]) -> Result<(), Error<T>> {
+        // Shipment existence check
+        ensure!(
+            !<Shipments<T>>::contains_key(id),
+            Error::<T>::ShipmentAlreadyExists
+        );
+        Ok(())
+    }
+
+    pub fn validate_custodian(id: &[u8], who: &T::AccountId) -> Result<(), Error<T>> {
+        ensure!(
+            <Custodians<T>>::get(id).as_ref() == Some(who),
+            Error::<T>::SenderIsNotCustodian
+        );
+        Ok(())
+    }
+
+    pub fn validate_owner_or_custodian(
+        shipment: &Shipment<T::AccountId, T::Moment>,
+        who: &T::AccountId,
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            &shipment.owner == who || <Custodians<T>>::get(&shipment.id).as_ref() == Some(who),
+            Error::<T>::SenderIsNotOwnerOrCustodian
+        );
+        Ok(())
+    }
+
+    pub fn validate_active_shipment(
+        id: &[u8],
+    ) -> Result<Shipment<T::AccountId, T::Moment>, Error<T>> {
+        match <Shipments<T>>::get(id) {
+            Some(shipment) => match shipment.status {
+                ShipmentStatus::AwaitingConfirmation => {
+                    Err(Error::<T>::ShipmentIsAwaitingConfirmation)
+                }
+                ShipmentStatus::Delivered => Err(Error::<T>::ShipmentHasBeenDelivered),
+                ShipmentStatus::Cancelled => Err(Error::<T>::ShipmentHasBeenCancelled),
+                ShipmentStatus::Disputed => Err(Error::<T>::ShipmentIsDisputed),
+                _ => Ok(shipment),
+            },
+            None => Err(Error::<T>::ShipmentIsUnknown),
+        }
+    }
+
+    pub fn validate_shipment_products(
+        owner: &T::AccountId,
+        props: &[ShipmentLine<T::Moment>],
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            props.len() <= SHIPMENT_MAX_PRODUCTS,
+            Error::<T>::ShipmentHasTooManyProducts,
+        );
+        for line in props {
+            ensure!(line.quantity > 0, Error::<T>::ShipmentHasInvalidQuantity);
+            if let Some(lot) = &line.lot {
+                Self::validate_lot_identifier(lot)?;
+            }
+            // Products must be registered & belong to the shipment's organization
+            match <product_registry::Module<T>>::owner_of(&line.product_id) {
+                Some(product_owner) => ensure!(
+                    &product_owner == owner,
+                    Error::<T>::ProductIsNotOwnedByOrganization
+                ),
+                None => return Err(Error::<T>::ProductIsUnknown),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_readings(
+        shipment_id: &[u8],
+        readings: &[Reading<T::Moment>],
+    ) -> Result<Vec<ReadingAnnotation>, Error<T>> {
+        if readings.is_empty() {
+            return Ok(vec![]);
+        }
+        let devices = <DevicesOfShipment>::get(shipment_id);
+        let mut annotations = Vec::with_capacity(readings.len());
+        for reading in readings {
+            let device =
+                <Devices<T>>::get(&reading.device_id).ok_or(Error::<T>::DeviceIsUnknown)?;
+            ensure!(
+                device.status == DeviceStatus::Active,
+                Error::<T>::DeviceIsDecommissioned
+            );
+            ensure!(
+                devices.contains(&reading.device_id),
+                Error::<T>::DeviceIsNotAttachedToShipment
+            );
+            ensure!(
+                device.supports(&reading.reading_type),
+                Error::<T>::DeviceDoesNotSupportReadingType
+            );
+            // A signature must be valid for the device's public key
+            let verified = Self::verify_reading_signature(&device, shipment_id, reading)?;
+            // Calibration in force at the reading's timestamp, if any
+            let calibrations = <Calibrations<T>>::get(&reading.device_id);
+            let calibration = calibrations
+                .iter()
+                .rposition(|calibration| calibration.calibrated <= reading.timestamp);
+            let (calibration_expired, calibrated_value) = match calibration {
+                Some(idx) => (
+                    calibrations[idx].has_expired_at(&reading.timestamp),
+                    calibrations[idx].correct(reading.value),
+                ),
+                None => (false, reading.value),
+            };
+            let corrected_value = reading
+                .unit
+                .to_canonical(calibrated_value)
+                .ok_or(Error::<T>::ReadingIsImplausible)?;
+            ensure!(
+                !calibration_expired || T::CalibrationPolicy::get() == CalibrationPolicy::Flag,
+                Error::<T>::DeviceCalibrationHasExpired
+            );
+            annotations.push(ReadingAnnotation {
+                verified,
+                calibration: calibration.map(|idx| idx as u32),
+                calibration_expired,
+                corrected_value,
+            });
+        }
+        Ok(annotations)
+    }
+
+    pub fn validate_calibration(
+        calibrations: &[Calibration<T::Moment>],
+        calibration: &Calibration<T::Moment>,
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            calibrations.len() < DEVICE_MAX_CALIBRATIONS,
+            Error::<T>::DeviceHasTooManyCalibrations
+        );
+        ensure!(
+            calibration.calibrated < calibration.valid_until,
+            Error::<T>::InvalidCalibration
+        );
+        ensure!(
+            calibration.scale != Decimal::from_num(0),
+            Error::<T>::InvalidCalibration
+        );
+        // Calibrations are recorded in chronological order
+        if let Some(last) = calibrations.last() {
+            ensure!(
+                last.calibrated <= calibration.calibrated,
+                Error::<T>::InvalidCalibration
+            );
+        }
+        Ok(())
+    }
+
+    // Returns the index of the first invalid reading, along with the error
+    pub fn validate_readings_plausibility(
+        shipment: &Shipment<T::AccountId, T::Moment>,
+        readings: &[Reading<T::Moment>],
+        timestamp: T::Moment,
+    ) -> Result<(), (usize, Error<T>)> {
+        for (idx, reading) in readings.iter().enumerate() {
+            // Readings must be taken during the shipment's lifetime, & before the event
+            if reading.timestamp < shipment.registered || reading.timestamp > timestamp {
+                return Err((idx, Error::<T>::ReadingTimestampIsInvalid));
+            }
+            // Readings' unit must be of their reading type's dimension
+            match Self::dimension_of(&reading.reading_type) {
+                Some(dimension) if dimension == reading.unit.dimension() => (),
+                Some(_) => return Err((idx, Error::<T>::ReadingUnitDoesNotMatchType)),
+                None => return Err((idx, Error::<T>::ReadingTypeIsUnknown)),
+            }
+            if let Some(bounds) = <PlausibilityBounds>::get(&reading.reading_type) {
+                match reading.unit.to_canonical(reading.value) {
+                    Some(value) if bounds.contain(value) => (),
+                    _ => return Err((idx, Error::<T>::ReadingIsImplausible)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_readings_payload(
+        payload: &ReadingsPayload<T::Moment>,
+        signature: &DeviceSignature,
+    ) -> Result<Device<T::AccountId, T::Moment>, Error<T>> {
+        // Unsigned transactions are free, so their size must be bounded
+        ensure!(
+            payload.readings.len() <= PAYLOAD_MAX_READINGS,
+            Error::<T>::ReadingsPayloadHasTooManyReadings
+        );
+
+        let device = <Devices<T>>::get(&payload.signer).ok_or(Error::<T>::DeviceIsUnknown)?;
+        ensure!(
+            device.status == DeviceStatus::Active,
+            Error::<T>::DeviceIsDecommissioned
+        );
+        let public_key = device
+            .public_key
+            .ok_or(Error::<T>::ReadingsPayloadSignatureIsInvalid)?;
+        ensure!(
+            sp_io::crypto::sr25519_verify(signature, &payload.encode(), &public_key),
+            Error::<T>::ReadingsPayloadSignatureIsInvalid
+        );
+
+        // Signer must be attached to the shipment
+        ensure!(
+            <DevicesOfShipment>::get(&payload.shipment_id).contains(&payload.signer),
+            Error::<T>::DeviceIsNotAttachedToShipment
+        );
+
+        // Readings of other devices must be signed by them (1 DB read per reading)
+        for reading in payload
+            .readings
+            .iter()
+            .filter(|reading| reading.device_id != payload.signer)
+        {
+            let other = <Devices<T>>::get(&reading.device_id).ok_or(Error::<T>::DeviceIsUnknown)?;
+            ensure!(
+                Self::verify_reading_signature(&other, &payload.shipment_id, reading)?,
+                Error::<T>::ReadingSignatureIsInvalid
+            );
+        }
+        Ok(device)
+    }
+
+    // Whether the reading is signed, failing if its signature isn't valid for the device's key
+    // & the shipment
+    fn verify_reading_signature(
+        device: &Device<T::AccountId, T::Moment>,
+        shipment_id: &[u8],
+        reading: &Reading<T::Moment>,
+    ) -> Result<bool, Error<T>> {
+        match &reading.signature {
+            Some(signature) => {
+                let public_key = device
+                    .public_key
+                    .as_ref()
+                    .ok_or(Error::<T>::ReadingSignatureIsInvalid)?;
+                ensure!(
+                    sp_io::crypto::sr25519_verify(
+                        signature,
+                        &reading.signing_payload(shipment_id),
+                        public_key
+                    ),
+                    Error::<T>::ReadingSignatureIsInvalid
+                );
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn verify_anchored_reading(
+        shipment_id: &[u8],
+        batch_idx: u32,
+        reading: &Reading<T::Moment>,
+        proof: &[H256],
+    ) -> bool {
+        match <BatchesOfShipment<T>>::get(shipment_id).get(batch_idx as usize) {
+            Some(batch) => {
+                batch.covers(&reading.timestamp)
+                    && merkle::verify_proof(&batch.root, &merkle::leaf_hash(reading), proof)
+            }
+            None => false,
+        }
+    }
+
+    pub fn validate_threshold_rules(rules: &[ThresholdRule]) -> Result<(), Error<T>> {
+        ensure!(
+            rules.len() <= THRESHOLD_MAX_RULES,
+            Error::<T>::TooManyThresholdRules
+        );
+        for rule in rules {
+            ensure!(
+                rule.min.is_some() || rule.max.is_some(),
+                Error::<T>::InvalidThresholdRule
+            );
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                ensure!(min <= max, Error::<T>::InvalidThresholdRule);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_delivery_note(
+        props: &[ShipmentLine<T::Moment>],
+        delivery_note: Option<DeliveryNote>,
+        delivered_on: T::Moment,
+    ) -> Result<Vec<Quantity>, Error<T>> {
+        let override_expiry = delivery_note
+            .as_ref()
+            .map(|note| note.override_expiry)
+            .unwrap_or(false);
+        ensure!(
+            override_expiry
+                || props
+                    .iter()
+                    .all(|line| line.expiry.map_or(true, |expiry| expiry >= delivered_on)),
+            Error::<T>::ShipmentHasExpiredProducts
+        );
+
+        match delivery_note {
+            // Without delivery note, shipment is delivered as registered
+            None => Ok(props.iter().map(|line| line.quantity).collect()),
+            Some(note) => {
+                ensure!(
+                    note.quantities.len() == props.len(),
+                    Error::<T>::InvalidDeliveryNote
+                );
+                Ok(note.quantities)
+            }
+        }
+    }
+
+    // --- Offchain worker methods ---
+
+    fn process_ocw_notifications(block_number: T::BlockNumber) {
+        // Check last processed block
+        let last_processed_block_ref =
+            StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block");
+        let mut last_processed_block: u32 = match last_processed_block_ref.get::<T::BlockNumber>() {
+            Some(Some(last_proccessed_block)) if last_proccessed_block >= block_number => {
+                debug::info!(
+                    "[product_tracking_ocw] Skipping: Block {:?} has already been processed.",
+                    block_number
+                );
+                return;
+            }
+            Some(Some(last_proccessed_block)) => {
+                last_proccessed_block.try_into().ok().unwrap() as u32
+            }
+            None => 0u32, //TODO: define a OCW_MAX_BACKTRACK_PERIOD param
+            _ => {
+                debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::last_proccessed_block.");
+                return;
+            }
+        };
+
+        let start_block = last_processed_block + 1;
+        let end_block = block_number.try_into().ok().unwrap() as u32;
+        for current_block in start_block..end_block {
+            debug::debug!(
+                "[product_tracking_ocw] Processing notifications for block {}",
+                current_block
+            );
+            let ev_indices = Self::ocw_notifications::<T::BlockNumber>(current_block.into());
+
+            let listener_results: Result<Vec<_>, _> = ev_indices
+                .iter()
+                .map(|idx| match Self::event_by_idx(idx) {
+                    Some(ev) => Self::notify_listener(&ev),
+                    None => Ok(()),
+                })
+                .collect();
+
+            if let Err(err) = listener_results {
+                debug::warn!("[product_tracking_ocw] notify_listener error: {}", err);
+                break;
+            }
+            last_processed_block = current_block;
+        }
+
+        // Save last processed block
+        if last_processed_block >= start_block {
+            last_processed_block_ref.set(&last_processed_block);
+            debug::info!(
+                "[product_tracking_ocw] Notifications successfully processed up to block {}",
+                last_processed_block
+            );
+        }
+    }
+
+    fn notify_listener(ev: &ShippingEvent<T::Moment>) -> Result<(), &'static str> {
+        debug::info!("notifying listener: {:?}", ev);
+
+        let request =
+            sp_runtime::offchain::http::Request::post(&LISTENER_ENDPOINT, vec![ev.to_string()]);
+
+        let timeout =
+            sp_io::offchain::timestamp().add(sp_runtime::offchain::Duration::from_millis(3000));
+
+        let pending = request
+            .add_header(&"Content-Type", &"text/plain")
+            .deadline(timeout) // Setting the timeout time
+            .send() // Sending the request out by the host
+            .map_err(|_| "http post request building error")?;
+
+        let response = pending
+            .try_wait(timeout)
+            .map_err(|_| "http post request sent error")?
+            .map_err(|_| "http post request sent error")?;
+
+        if response.code != 200 {
+            return Err("http response error");
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Trait> ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        if let Call::submit_readings_unsigned(payload, signature) = call {
+            // Payload must be signed by an active device attached to the shipment, & the
+            // readings of other devices must be signed by them
+            Self::validate_readings_payload(payload, signature)
+                .map_err(|_| InvalidTransaction::BadProof)?;
+
+            // Replay protection: a device's payloads are included in nonce order
+            let nonce = <DeviceNonces>::get(&payload.signer);
+            if payload.nonce < nonce {
+                return InvalidTransaction::Stale.into();
+            }
+
+            let mut tx = ValidTransaction::with_tag_prefix("ProductTracking")
+                .priority(T::UnsignedPriority::get())
+                .and_provides((payload.signer.clone(), payload.nonce))
+                .longevity(UNSIGNED_READINGS_LONGEVITY)
+                .propagate(true);
+            if payload.nonce > nonce {
+                tx = tx.and_requires((payload.signer.clone(), payload.nonce - 1));
+            }
+            tx.build()
+        } else {
+            InvalidTransaction::Call.into()
+        }
     }
 }
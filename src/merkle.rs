@@ -0,0 +1,69 @@
+//! Merkle trees of readings, for batches of readings kept off-chain & anchored by their root.
+//!
+//! Leaves & nodes are hashed with different prefixes, so that a node can't be passed off as a leaf.
+//! The two children of a node are sorted before being hashed, so that inclusion proofs don't need
+//! to tell on which side each sibling is. A node without sibling is promoted to the next level.
+
+use codec::Encode;
+use frame_support::sp_std::prelude::*;
+use sp_core::H256;
+use sp_io::hashing::blake2_256;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn leaf_hash<T: Encode>(leaf: &T) -> H256 {
+    let mut data = vec![LEAF_PREFIX];
+    leaf.encode_to(&mut data);
+    H256(blake2_256(&data))
+}
+
+pub fn node_hash(a: &H256, b: &H256) -> H256 {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = Vec::with_capacity(1 + 2 * H256::len_bytes());
+    data.push(NODE_PREFIX);
+    data.extend_from_slice(left.as_bytes());
+    data.extend_from_slice(right.as_bytes());
+    H256(blake2_256(&data))
+}
+
+pub fn root(leaves: &[H256]) -> Option<H256> {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level.first().copied()
+}
+
+pub fn proof(leaves: &[H256], mut index: usize) -> Option<Vec<H256>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut proof = vec![];
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = parent_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+pub fn verify_proof(root: &H256, leaf: &H256, proof: &[H256]) -> bool {
+    proof
+        .iter()
+        .fold(*leaf, |hash, sibling| node_hash(&hash, sibling))
+        == *root
+}
+
+fn parent_level(level: &[H256]) -> Vec<H256> {
+    level
+        .chunks(2)
+        .map(|pair| {
+            pair.get(1)
+                .map_or(pair[0], |right| node_hash(&pair[0], right))
+        })
+        .collect()
+}
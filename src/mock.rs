@@ -1,12 +1,17 @@
 // Creating mock runtime here
 
-use crate::{Call, Module, Trait};
-use core::marker::PhantomData;
+use crate::{
+    types::{CalibrationPolicy, EmissionFactors},
+    Call, Module, Trait,
+};
+use core::{cell::RefCell, marker::PhantomData};
 use frame_support::{
-    impl_outer_event, impl_outer_origin, parameter_types, traits::EnsureOrigin, weights::Weight,
+    impl_outer_event, impl_outer_origin, parameter_types,
+    traits::{EnsureOrigin, Get},
+    weights::Weight,
 };
 use frame_system as system;
-use frame_system::RawOrigin;
+use frame_system::{EnsureRoot, RawOrigin};
 use sp_core::{
     // offchain::{
     //     testing::{self, OffchainState, PoolState},
@@ -19,6 +24,7 @@ use sp_core::{
 use sp_runtime::{
     testing::{Header, TestXt},
     traits::{BlakeTwo256, IdentityLookup},
+    transaction_validity::TransactionPriority,
     Perbill,
 };
 
@@ -31,6 +37,7 @@ impl_outer_origin! {
 impl_outer_event! {
     pub enum TestEvent for Test {
         system<T>,
+        product_registry<T>,
         product_tracking<T>,
     }
 }
@@ -83,9 +90,53 @@ impl timestamp::Trait for Test {
     type WeightInfo = ();
 }
 
+impl product_registry::Trait for Test {
+    type Event = TestEvent;
+    type CreateRoleOrigin = MockOrigin<Test>;
+}
+
+parameter_types! {
+    pub const DeliveryConfirmationTimeout: u64 = 10;
+    pub const UnsignedPriority: TransactionPriority = 100;
+    pub const MockEmissionFactors: EmissionFactors = EmissionFactors {
+        road: 100,
+        rail: 30,
+        sea: 15,
+        air: 600,
+    };
+    pub const DwellLimit: u64 = 3_600_000; // 1 hour
+    pub const DwellRadius: u32 = 500;
+    pub const IdleTimeout: u64 = 20;
+}
+
 impl Trait for Test {
     type Event = TestEvent;
     type CreateRoleOrigin = MockOrigin<Test>;
+    type DeliveryConfirmationTimeout = DeliveryConfirmationTimeout;
+    type RecallOrigin = EnsureRoot<sr25519::Public>;
+    type UnsignedPriority = UnsignedPriority;
+    type CalibrationPolicy = MockCalibrationPolicy;
+    type AdminOrigin = EnsureRoot<sr25519::Public>;
+    type EmissionFactors = MockEmissionFactors;
+    type DwellLimit = DwellLimit;
+    type DwellRadius = DwellRadius;
+    type IdleTimeout = IdleTimeout;
+}
+
+thread_local! {
+    static CALIBRATION_POLICY: RefCell<CalibrationPolicy> = RefCell::new(CalibrationPolicy::Flag);
+}
+
+pub struct MockCalibrationPolicy;
+
+impl Get<CalibrationPolicy> for MockCalibrationPolicy {
+    fn get() -> CalibrationPolicy {
+        CALIBRATION_POLICY.with(|policy| policy.borrow().clone())
+    }
+}
+
+pub fn set_calibration_policy(policy: CalibrationPolicy) {
+    CALIBRATION_POLICY.with(|p| *p.borrow_mut() = policy);
 }
 
 pub type ProductTracking = Module<Test>;
@@ -123,6 +174,10 @@ pub fn account_key(s: &str) -> sr25519::Public {
         .public()
 }
 
+pub fn device_pair(s: &str) -> sr25519::Pair {
+    sr25519::Pair::from_string(&format!("//{}", s), None).expect("static values are valid; qed")
+}
+
 // Offchain worker
 
 type TestExtrinsic = TestXt<Call<Test>, ()>;
@@ -1,34 +1,101 @@
 use super::*;
 use crate::{mock::*, types::*, Error};
 use fixed::types::I16F16;
-use frame_support::{assert_noop, assert_ok, dispatch};
+use frame_support::{assert_noop, assert_ok, dispatch, traits::OnInitialize};
+use sp_core::{Pair, H256};
+use sp_runtime::transaction_validity::TransactionValidityError;
 
 pub fn store_test_shipment<T: Trait>(
     id: ShipmentId,
     owner: T::AccountId,
     status: ShipmentStatus,
-    products: Vec<ProductId>,
+    products: Vec<ShipmentLine<T::Moment>>,
     registered: T::Moment,
 ) {
+    Custodians::<T>::insert(&id, &owner);
+    let consignee = owner.clone();
     Shipments::<T>::insert(
         id.clone(),
         Shipment {
             id,
             owner,
+            consignee,
             status,
             products,
             registered,
             delivered: None,
+            damaged: false,
+            return_of: None,
+            returned_as: None,
+            delivered_quantities: vec![],
+            fulfilment: None,
+            recalled: false,
+            compliant: true,
+            deviations: 0,
+            transport_mode: None,
+            distance: 0,
+            emissions: None,
         },
     );
 }
 
+pub fn test_line(product_id: &str, quantity: Quantity) -> ShipmentLine<u64> {
+    ShipmentLine {
+        product_id: product_id.as_bytes().to_owned(),
+        quantity,
+        unit: UnitOfMeasure::Case,
+        lot: None,
+        expiry: None,
+    }
+}
+
+pub fn store_test_product<T: Trait>(id: ProductId, owner: T::AccountId) {
+    product_registry::OwnerOf::<T>::insert(id, owner);
+}
+
+pub fn store_test_device<T: Trait>(id: DeviceId, owner: T::AccountId, shipment_id: &[u8]) {
+    Devices::<T>::insert(
+        &id,
+        Device {
+            id: id.clone(),
+            owner,
+            reading_types: vec![
+                ReadingType::Humidity,
+                ReadingType::Shock,
+                ReadingType::Temperature,
+            ],
+            public_key: None,
+            status: DeviceStatus::Active,
+            registered: 42.into(),
+        },
+    );
+    DevicesOfShipment::append(shipment_id, id);
+}
+
+pub fn store_test_facility<T: Trait>(id: FacilityId, owner: T::AccountId, geofence: Geofence) {
+    Facilities::<T>::insert(
+        &id,
+        Facility {
+            id: id.clone(),
+            owner,
+            address_hash: H256::zero(),
+            geofence: geofence.clone(),
+            registered: 42.into(),
+        },
+    );
+    for cell in geofence.cells(FACILITY_MAX_CELLS).unwrap() {
+        FacilitiesOfCell::append(cell, &id);
+    }
+}
+
 pub fn store_test_event<T: Trait>(shipment_id: ShipmentId, event_type: ShippingEventType) {
     let event = ShippingEvent {
         event_type,
         shipment_id: shipment_id.clone(),
         location: None,
+        facility: None,
         readings: vec![],
+        annotations: vec![],
         timestamp: 42.into(),
     };
     let event_idx = EventCount::get().checked_add(1).unwrap();
@@ -39,8 +106,11 @@ pub fn store_test_event<T: Trait>(shipment_id: ShipmentId, event_type: ShippingE
 
 const TEST_PRODUCT_ID: &str = "00012345678905";
 const TEST_SHIPMENT_ID: &str = "0001";
+const TEST_DEVICE_ID: &str = "14d453ea4bdf46bc8042";
 const TEST_ORGANIZATION: &str = "Northwind";
 const TEST_SENDER: &str = "Alice";
+const TEST_CARRIER: &str = "Bob";
+const TEST_CONSIGNEE: &str = "Charlie";
 const LONG_VALUE : &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Donec aliquam ut tortor nec congue. Pellente";
 
 #[test]
@@ -49,6 +119,7 @@ fn register_shipment_without_products() {
         let sender = account_key(TEST_SENDER);
         let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
         let owner = account_key(TEST_ORGANIZATION);
+        let consignee = account_key(TEST_CONSIGNEE);
         let now = 42;
         Timestamp::set_timestamp(now);
 
@@ -56,6 +127,7 @@ fn register_shipment_without_products() {
             Origin::signed(sender),
             id.clone(),
             owner.clone(),
+            consignee.clone(),
             vec![],
         );
 
@@ -66,10 +138,22 @@ fn register_shipment_without_products() {
             Some(Shipment {
                 id: id.clone(),
                 owner: owner,
+                consignee: consignee,
                 status: ShipmentStatus::Pending,
                 products: vec![],
                 registered: now,
-                delivered: None
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
 
@@ -77,6 +161,7 @@ fn register_shipment_without_products() {
             <ShipmentsOfOrganization<Test>>::get(owner),
             vec![id.clone()]
         );
+        assert_eq!(ProductTracking::custodian_of(&id), Some(owner));
 
         assert!(System::events().iter().any(|er| er.event
             == TestEvent::product_tracking(RawEvent::ShipmentRegistered(
@@ -101,17 +186,24 @@ fn register_shipment_with_valid_products() {
         let sender = account_key(TEST_SENDER);
         let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
         let owner = account_key(TEST_ORGANIZATION);
+        let consignee = account_key(TEST_CONSIGNEE);
         let now = 42;
         Timestamp::set_timestamp(now);
 
+        // Products are registered by the organization
+        store_test_product::<Test>(b"00012345600001".to_vec(), owner);
+        store_test_product::<Test>(b"00012345600002".to_vec(), owner);
+        store_test_product::<Test>(b"00012345600003".to_vec(), owner);
+
         let result = ProductTracking::register_shipment(
             Origin::signed(sender),
             id.clone(),
             owner.clone(),
+            consignee.clone(),
             vec![
-                b"00012345600001".to_vec(),
-                b"00012345600002".to_vec(),
-                b"00012345600003".to_vec(),
+                test_line("00012345600001", 10),
+                test_line("00012345600002", 10),
+                test_line("00012345600003", 10),
             ],
         );
 
@@ -122,14 +214,26 @@ fn register_shipment_with_valid_products() {
             Some(Shipment {
                 id: id.clone(),
                 owner: owner,
+                consignee: consignee,
                 status: ShipmentStatus::Pending,
                 products: vec![
-                    b"00012345600001".to_vec(),
-                    b"00012345600002".to_vec(),
-                    b"00012345600003".to_vec(),
+                    test_line("00012345600001", 10),
+                    test_line("00012345600002", 10),
+                    test_line("00012345600003", 10),
                 ],
                 registered: now,
-                delivered: None
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
 
@@ -163,6 +267,7 @@ fn register_shipment_with_invalid_sender() {
                 Origin::none(),
                 TEST_SHIPMENT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
+                account_key(TEST_CONSIGNEE),
                 vec!()
             ),
             dispatch::DispatchError::BadOrigin
@@ -178,6 +283,7 @@ fn register_shipment_with_missing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 vec!(),
                 account_key(TEST_ORGANIZATION),
+                account_key(TEST_CONSIGNEE),
                 vec!()
             ),
             Error::<Test>::InvalidOrMissingIdentifier
@@ -193,6 +299,7 @@ fn register_shipment_with_long_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 LONG_VALUE.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
+                account_key(TEST_CONSIGNEE),
                 vec!()
             ),
             Error::<Test>::InvalidOrMissingIdentifier
@@ -219,6 +326,7 @@ fn register_shipment_with_existing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 existing_shipment,
                 account_key(TEST_ORGANIZATION),
+                account_key(TEST_CONSIGNEE),
                 vec![]
             ),
             Error::<Test>::ShipmentAlreadyExists
@@ -234,18 +342,19 @@ fn register_shipment_with_too_many_products() {
                 Origin::signed(account_key(TEST_SENDER)),
                 TEST_SHIPMENT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
+                account_key(TEST_CONSIGNEE),
                 vec![
-                    b"00012345600001".to_vec(),
-                    b"00012345600002".to_vec(),
-                    b"00012345600003".to_vec(),
-                    b"00012345600004".to_vec(),
-                    b"00012345600005".to_vec(),
-                    b"00012345600006".to_vec(),
-                    b"00012345600007".to_vec(),
-                    b"00012345600008".to_vec(),
-                    b"00012345600009".to_vec(),
-                    b"00012345600010".to_vec(),
-                    b"00012345600011".to_vec(),
+                    test_line("00012345600001", 10),
+                    test_line("00012345600002", 10),
+                    test_line("00012345600003", 10),
+                    test_line("00012345600004", 10),
+                    test_line("00012345600005", 10),
+                    test_line("00012345600006", 10),
+                    test_line("00012345600007", 10),
+                    test_line("00012345600008", 10),
+                    test_line("00012345600009", 10),
+                    test_line("00012345600010", 10),
+                    test_line("00012345600011", 10),
                 ]
             ),
             Error::<Test>::ShipmentHasTooManyProducts
@@ -253,6 +362,51 @@ fn register_shipment_with_too_many_products() {
     })
 }
 
+#[test]
+fn register_shipment_with_unknown_product() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        store_test_product::<Test>(b"00012345600001".to_vec(), owner);
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                owner,
+                account_key(TEST_CONSIGNEE),
+                vec![
+                    test_line("00012345600001", 10),
+                    test_line("00012345600002", 10)
+                ]
+            ),
+            Error::<Test>::ProductIsUnknown
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_product_of_another_organization() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        store_test_product::<Test>(b"00012345600001".to_vec(), owner);
+        store_test_product::<Test>(b"00012345600002".to_vec(), account_key(TEST_CARRIER));
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                owner,
+                account_key(TEST_CONSIGNEE),
+                vec![
+                    test_line("00012345600001", 10),
+                    test_line("00012345600002", 10)
+                ]
+            ),
+            Error::<Test>::ProductIsNotOwnedByOrganization
+        );
+    })
+}
+
 #[test]
 fn track_shipment_with_invalid_sender() {
     new_test_ext().execute_with(|| {
@@ -265,6 +419,8 @@ fn track_shipment_with_invalid_sender() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             dispatch::DispatchError::BadOrigin
@@ -284,6 +440,8 @@ fn track_shipment_with_missing_shipment_id() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             Error::<Test>::InvalidOrMissingIdentifier
@@ -303,6 +461,8 @@ fn track_shipment_with_long_shipment_id() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             Error::<Test>::InvalidOrMissingIdentifier,
@@ -323,6 +483,8 @@ fn track_shipment_with_unknown_shipment() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             Error::<Test>::ShipmentIsUnknown,
@@ -342,7 +504,7 @@ fn track_shipment_pickup() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::Pending,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
 
@@ -356,6 +518,8 @@ fn track_shipment_pickup() {
             ShippingOperation::Pickup,
             now,
             None,
+            None,
+            None,
             None
         ));
 
@@ -367,7 +531,9 @@ fn track_shipment_pickup() {
                 event_type: ShippingEventType::ShipmentPickup,
                 shipment_id: shipment_id.clone(),
                 location: None,
+                facility: None,
                 readings: vec![],
+                annotations: vec![],
                 timestamp: now,
             })
         );
@@ -379,10 +545,22 @@ fn track_shipment_pickup() {
             Some(Shipment {
                 id: shipment_id.clone(),
                 owner: owner,
+                consignee: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
                 registered: now,
-                delivered: None
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
 
@@ -410,7 +588,7 @@ fn track_shipment_delivery() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
 
@@ -425,6 +603,8 @@ fn track_shipment_delivery() {
             ShippingOperation::Deliver,
             now,
             None,
+            None,
+            None,
             None
         ));
 
@@ -436,23 +616,37 @@ fn track_shipment_delivery() {
                 event_type: ShippingEventType::ShipmentDeliver,
                 shipment_id: shipment_id.clone(),
                 location: None,
+                facility: None,
                 readings: vec![],
+                annotations: vec![],
                 timestamp: now,
             })
         );
         assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2, 3]);
 
-        // Shipment's status should be updated to 'Delivered'
+        // Shipment's status should be updated to 'AwaitingConfirmation'
         // and delivered timestamp updated
         assert_eq!(
             ProductTracking::shipment_by_id(&shipment_id),
             Some(Shipment {
                 id: shipment_id.clone(),
                 owner: owner,
-                status: ShipmentStatus::Delivered,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                consignee: owner,
+                status: ShipmentStatus::AwaitingConfirmation,
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
                 registered: now,
-                delivered: Some(now)
+                delivered: Some(now),
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![10],
+                fulfilment: Some(Fulfilment::Complete),
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
 
@@ -462,7 +656,7 @@ fn track_shipment_delivery() {
                 owner,
                 shipment_id.clone(),
                 3,
-                ShipmentStatus::Delivered
+                ShipmentStatus::AwaitingConfirmation
             ))));
     })
 }
@@ -479,7 +673,7 @@ fn track_shipment_for_delivered_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::Delivered,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
 
@@ -490,6 +684,8 @@ fn track_shipment_for_delivered_shipment() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             Error::<Test>::ShipmentHasBeenDelivered
@@ -509,7 +705,7 @@ fn track_shipment_for_intransit_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
 
@@ -520,6 +716,8 @@ fn track_shipment_for_intransit_shipment() {
                 ShippingOperation::Pickup,
                 now,
                 None,
+                None,
+                None,
                 None
             ),
             Error::<Test>::ShipmentIsInTransit
@@ -539,9 +737,10 @@ fn monitor_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
 
         // Store shipping registration & pickup events
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
@@ -549,15 +748,20 @@ fn monitor_shipment() {
 
         // Define location & readings for sensor reading
         let location = ReadPoint {
-            latitude: I16F16::from_num(52.4941126),
-            longitude: I16F16::from_num(13.4355606),
+            latitude: Coordinate::from_num(52.4941126),
+            longitude: Coordinate::from_num(13.4355606),
+            altitude: Some(I16F16::from_num(34)),
+            accuracy: Some(I16F16::from_num(5)),
+            source: LocationSource::Gps,
         };
 
         let readings = vec![Reading {
-            device_id: "14d453ea4bdf46bc8042".as_bytes().to_owned(),
+            device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
             reading_type: ReadingType::Temperature,
             value: I16F16::from_num(20.123),
             timestamp: now,
+            unit: ReadingUnit::Celsius,
+            signature: None,
         }];
 
         // Dispatchable call succeeds
@@ -567,7 +771,9 @@ fn monitor_shipment() {
             ShippingOperation::Scan,
             now,
             Some(location.clone()),
-            Some(readings.clone())
+            Some(readings.clone()),
+            None,
+            None
         ));
 
         // Storage is correctly updated
@@ -578,7 +784,14 @@ fn monitor_shipment() {
                 event_type: ShippingEventType::ShipmentScan,
                 shipment_id: shipment_id.clone(),
                 location: Some(location),
+                facility: None,
                 readings: readings,
+                annotations: vec![ReadingAnnotation {
+                    verified: false,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(20.123),
+                }],
                 timestamp: now,
             })
         );
@@ -590,10 +803,22 @@ fn monitor_shipment() {
             Some(Shipment {
                 id: shipment_id.clone(),
                 owner: owner,
+                consignee: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
                 registered: now,
-                delivered: None
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
     })
@@ -611,9 +836,10 @@ fn monitor_shipment_with_negative_latlon() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![test_line(TEST_PRODUCT_ID, 10)],
             now,
         );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
 
         // Store shipping registration & pickup events
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
@@ -622,15 +848,20 @@ fn monitor_shipment_with_negative_latlon() {
         // Define location & readings for sensor reading
         let location = ReadPoint {
             // Rio de Janeiro, Brazil
-            latitude: I16F16::from_num(-22.9466369),
-            longitude: I16F16::from_num(-43.233472),
+            latitude: Coordinate::from_num(-22.9466369),
+            longitude: Coordinate::from_num(-43.233472),
+            altitude: None,
+            accuracy: None,
+            source: LocationSource::Cell,
         };
 
         let readings = vec![Reading {
-            device_id: "14d453ea4bdf46bc8042".as_bytes().to_owned(),
+            device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
             reading_type: ReadingType::Temperature,
             value: I16F16::from_num(20.123),
             timestamp: now,
+            unit: ReadingUnit::Celsius,
+            signature: None,
         }];
 
         // Dispatchable call succeeds
@@ -640,7 +871,9 @@ fn monitor_shipment_with_negative_latlon() {
             ShippingOperation::Scan,
             now,
             Some(location.clone()),
-            Some(readings.clone())
+            Some(readings.clone()),
+            None,
+            None
         ));
 
         // Storage is correctly updated
@@ -651,7 +884,14 @@ fn monitor_shipment_with_negative_latlon() {
                 event_type: ShippingEventType::ShipmentScan,
                 shipment_id: shipment_id.clone(),
                 location: Some(location),
+                facility: None,
                 readings: readings,
+                annotations: vec![ReadingAnnotation {
+                    verified: false,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(20.123),
+                }],
                 timestamp: now,
             })
         );
@@ -663,11 +903,3191 @@ fn monitor_shipment_with_negative_latlon() {
             Some(Shipment {
                 id: shipment_id.clone(),
                 owner: owner,
+                consignee: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
+                registered: now,
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
+            })
+        );
+    })
+}
+
+#[test]
+fn cancel_pending_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ Pending status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Pending,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Store shipping registration event
+        store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
+
+        // Only the shipment's owner or custodian can cancel it
+        assert_noop!(
+            ProductTracking::cancel_shipment(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone(),
+                CancellationReason::Duplicate
+            ),
+            Error::<Test>::SenderIsNotOwnerOrCustodian
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::cancel_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            CancellationReason::Duplicate
+        ));
+
+        // Storage is correctly updated
+        assert_eq!(EventCount::get(), 2);
+        assert_eq!(
+            AllEvents::<Test>::get(2),
+            Some(ShippingEvent {
+                event_type: ShippingEventType::ShipmentCancellation(CancellationReason::Duplicate),
+                shipment_id: shipment_id.clone(),
+                location: None,
+                facility: None,
+                readings: vec![],
+                annotations: vec![],
+                timestamp: now,
+            })
+        );
+        assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2]);
+
+        // Shipment's status should be updated to 'Cancelled'
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id),
+            Some(Shipment {
+                id: shipment_id.clone(),
+                owner: owner,
+                consignee: owner,
+                status: ShipmentStatus::Cancelled,
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
                 registered: now,
-                delivered: None
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
             })
         );
+
+        // Events are raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentCancelled(
+                owner,
+                shipment_id.clone(),
+                CancellationReason::Duplicate
+            ))));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentStatusUpdated(
+                owner,
+                shipment_id.clone(),
+                2,
+                ShipmentStatus::Cancelled
+            ))));
+    })
+}
+
+#[test]
+fn cancel_intransit_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        assert_noop!(
+            ProductTracking::cancel_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                CancellationReason::OrderCancelled
+            ),
+            Error::<Test>::ShipmentIsInTransit
+        );
+    })
+}
+
+#[test]
+fn track_shipment_for_cancelled_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ Cancelled status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Cancelled,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentHasBeenCancelled
+        );
+    })
+}
+
+#[test]
+fn track_shipment_exception_for_pending_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ Pending status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Pending,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::ReportDelay,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentIsPending
+        );
+    })
+}
+
+#[test]
+fn track_shipment_exception_by_non_custodian() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let finder = account_key(TEST_CARRIER);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        let track = |who, operation| {
+            ProductTracking::track_shipment(
+                Origin::signed(who),
+                shipment_id.clone(),
+                operation,
+                now,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+
+        // Only the current custodian can report exceptions
+        for operation in vec![
+            ShippingOperation::ReportDamage,
+            ShippingOperation::ReportLoss,
+            ShippingOperation::ReportDelay,
+        ] {
+            assert_noop!(
+                track(finder, operation),
+                Error::<Test>::SenderIsNotCustodian
+            );
+        }
+        assert_ok!(track(owner, ShippingOperation::ReportLoss));
+
+        // Anyone can scan a lost shipment they've found, but not deliver it
+        assert_ok!(track(finder, ShippingOperation::Scan));
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::InTransit)
+        );
+        assert_noop!(
+            track(finder, ShippingOperation::Scan),
+            Error::<Test>::SenderIsNotCustodian
+        );
+        assert_noop!(
+            track(finder, ShippingOperation::Deliver),
+            Error::<Test>::SenderIsNotCustodian
+        );
+    })
+}
+
+#[test]
+fn track_shipment_damaged_then_delivered() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Store shipping registration & pickup events
+        store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
+        store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentPickup);
+
+        // Damage is reported
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::ReportDamage,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+
+        assert_eq!(
+            AllEvents::<Test>::get(3).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentDamageReport)
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentDamaged(
+                owner,
+                shipment_id.clone(),
+                3
+            ))));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentStatusUpdated(
+                owner,
+                shipment_id.clone(),
+                3,
+                ShipmentStatus::Damaged
+            ))));
+
+        // Damaged shipment can still be delivered
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+
+        // Shipment is delivered (pending confirmation) but remains flagged as damaged
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id),
+            Some(Shipment {
+                id: shipment_id.clone(),
+                owner: owner,
+                consignee: owner,
+                status: ShipmentStatus::AwaitingConfirmation,
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
+                registered: now,
+                delivered: Some(now),
+                damaged: true,
+                return_of: None,
+                returned_as: None,
+                delivered_quantities: vec![10],
+                fulfilment: Some(Fulfilment::Complete),
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
+            })
+        );
+    })
+}
+
+#[test]
+fn track_shipment_lost_then_found() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ Lost status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Lost,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Lost shipment cannot be delivered
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentIsLost
+        );
+
+        // Scanning a lost shipment means it has been found again
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::InTransit)
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentStatusUpdated(
+                owner,
+                shipment_id.clone(),
+                1,
+                ShipmentStatus::InTransit
+            ))));
+    })
+}
+
+#[test]
+fn initiate_return_of_delivered_shipment() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let original_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let return_id = b"R0001".to_vec();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ Delivered status
+        store_test_shipment::<Test>(
+            original_id.clone(),
+            owner,
+            ShipmentStatus::Delivered,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+        Shipments::<Test>::mutate(&original_id, |shipment| {
+            if let Some(shipment) = shipment {
+                shipment.consignee = sender;
+            }
+        });
+
+        // Only the consignee or the owner can return the shipment
+        assert_noop!(
+            ProductTracking::initiate_return(
+                Origin::signed(account_key(TEST_CARRIER)),
+                original_id.clone(),
+                return_id.clone(),
+                account_key(TEST_CARRIER)
+            ),
+            Error::<Test>::SenderIsNotConsigneeOrOwner
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::initiate_return(
+            Origin::signed(sender),
+            original_id.clone(),
+            return_id.clone(),
+            sender
+        ));
+
+        // Original shipment references its return shipment
+        assert_eq!(
+            ProductTracking::shipment_by_id(&original_id),
+            Some(Shipment {
+                id: original_id.clone(),
+                owner: owner,
+                consignee: sender,
+                status: ShipmentStatus::Delivered,
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
+                registered: now,
+                delivered: None,
+                damaged: false,
+                return_of: None,
+                returned_as: Some(return_id.clone()),
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
+            })
+        );
+
+        // Return shipment carries the original shipment's products
+        assert_eq!(
+            ProductTracking::shipment_by_id(&return_id),
+            Some(Shipment {
+                id: return_id.clone(),
+                owner: sender,
+                consignee: owner,
+                status: ShipmentStatus::Pending,
+                products: vec![test_line(TEST_PRODUCT_ID, 10)],
+                registered: now,
+                delivered: None,
+                damaged: false,
+                return_of: Some(original_id.clone()),
+                returned_as: None,
+                delivered_quantities: vec![],
+                fulfilment: None,
+                recalled: false,
+                compliant: true,
+                deviations: 0,
+                transport_mode: None,
+                distance: 0,
+                emissions: None
+            })
+        );
+        assert_eq!(
+            <ShipmentsOfOrganization<Test>>::get(sender),
+            vec![return_id.clone()]
+        );
+
+        // Both shipments have their own shipping events
+        assert_eq!(EventCount::get(), 2);
+        assert_eq!(
+            AllEvents::<Test>::get(1).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentReturn)
+        );
+        assert_eq!(EventsOfShipment::get(&original_id), vec![1]);
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentRegistration)
+        );
+        assert_eq!(EventsOfShipment::get(&return_id), vec![2]);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentReturnInitiated(
+                sender,
+                original_id.clone(),
+                return_id.clone()
+            ))));
+
+        // A shipment can only be returned once
+        assert_noop!(
+            ProductTracking::initiate_return(
+                Origin::signed(sender),
+                original_id.clone(),
+                b"R0002".to_vec(),
+                sender
+            ),
+            Error::<Test>::ShipmentHasBeenReturned
+        );
+    })
+}
+
+#[test]
+fn initiate_return_of_intransit_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        assert_noop!(
+            ProductTracking::initiate_return(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                b"R0001".to_vec(),
+                owner
+            ),
+            Error::<Test>::ShipmentIsNotDelivered
+        );
+    })
+}
+
+#[test]
+fn hand_over_shipment_custody() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let carrier = account_key(TEST_CARRIER);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ Pending status, owner has custody of the shipment
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Pending,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Only the custodian can offer the handover
+        assert_noop!(
+            ProductTracking::offer_custody(Origin::signed(carrier), shipment_id.clone(), carrier),
+            Error::<Test>::SenderIsNotCustodian
+        );
+        assert_ok!(ProductTracking::offer_custody(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            carrier
+        ));
+        assert_eq!(
+            ProductTracking::pending_handover_of(&shipment_id),
+            Some(carrier)
+        );
+
+        // Only the receiver can accept the handover
+        assert_noop!(
+            ProductTracking::accept_custody(Origin::signed(owner), shipment_id.clone()),
+            Error::<Test>::SenderIsNotHandoverReceiver
+        );
+        assert_ok!(ProductTracking::accept_custody(
+            Origin::signed(carrier),
+            shipment_id.clone()
+        ));
+
+        // Storage is correctly updated
+        assert_eq!(EventCount::get(), 2);
+        assert_eq!(
+            AllEvents::<Test>::get(1).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentHandoverOffer)
+        );
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentHandoverAcceptance)
+        );
+        assert_eq!(ProductTracking::custodian_of(&shipment_id), Some(carrier));
+        assert_eq!(ProductTracking::pending_handover_of(&shipment_id), None);
+        assert_eq!(
+            ProductTracking::custody_history(&shipment_id),
+            vec![(carrier, 2)]
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::CustodyTransferred(
+                owner,
+                shipment_id.clone(),
+                carrier
+            ))));
+
+        // Previous custodian cannot scan the shipment anymore
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::SenderIsNotCustodian
+        );
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(carrier),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+    })
+}
+
+#[test]
+fn confirm_delivery_by_consignee() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ InTransit status, owner is also the consignee
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Shipment is delivered
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+        assert_eq!(
+            ProductTracking::delivery_deadlines(1 + DeliveryConfirmationTimeout::get()),
+            vec![shipment_id.clone()]
+        );
+
+        // Only the consignee can confirm the delivery
+        assert_noop!(
+            ProductTracking::confirm_delivery(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone()
+            ),
+            Error::<Test>::SenderIsNotConsignee
+        );
+        assert_ok!(ProductTracking::confirm_delivery(
+            Origin::signed(owner),
+            shipment_id.clone()
+        ));
+
+        // Storage is correctly updated
+        assert_eq!(EventCount::get(), 2);
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentDeliveryConfirmation)
+        );
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::Delivered)
+        );
+
+        // Events are raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeliveryConfirmed(
+                owner,
+                shipment_id.clone(),
+                2
+            ))));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentStatusUpdated(
+                owner,
+                shipment_id.clone(),
+                2,
+                ShipmentStatus::Delivered
+            ))));
+
+        // Delivery cannot be confirmed twice
+        assert_noop!(
+            ProductTracking::confirm_delivery(Origin::signed(owner), shipment_id.clone()),
+            Error::<Test>::ShipmentHasBeenDelivered
+        );
+    })
+}
+
+#[test]
+fn confirm_delivery_after_timeout() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Shipment is delivered
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+
+        // Nothing happens before the deadline
+        let deadline = 1 + DeliveryConfirmationTimeout::get();
+        ProductTracking::on_initialize(deadline - 1);
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::AwaitingConfirmation)
+        );
+
+        // Delivery is confirmed once the deadline is reached
+        System::set_block_number(deadline);
+        ProductTracking::on_initialize(deadline);
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::Delivered)
+        );
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentDeliveryConfirmation)
+        );
+        assert_eq!(
+            ProductTracking::delivery_deadlines(deadline),
+            Vec::<ShipmentId>::new()
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeliveryConfirmationTimedOut(
+                shipment_id.clone(),
+                2
+            ))));
+    })
+}
+
+#[test]
+fn dispute_delivery() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status, consigned to its owner
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Shipment is delivered
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+
+        // Only the consignee can dispute the delivery
+        assert_noop!(
+            ProductTracking::dispute_delivery(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone()
+            ),
+            Error::<Test>::SenderIsNotConsignee
+        );
+        assert_ok!(ProductTracking::dispute_delivery(
+            Origin::signed(owner),
+            shipment_id.clone()
+        ));
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::Disputed)
+        );
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|ev| ev.event_type),
+            Some(ShippingEventType::ShipmentDeliveryDispute)
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeliveryDisputed(
+                owner,
+                shipment_id.clone(),
+                2
+            ))));
+        assert_noop!(
+            ProductTracking::dispute_delivery(Origin::signed(owner), shipment_id.clone()),
+            Error::<Test>::ShipmentIsDisputed
+        );
+
+        // Disputed delivery isn't confirmed once the deadline is reached
+        let deadline = 1 + DeliveryConfirmationTimeout::get();
+        System::set_block_number(deadline);
+        ProductTracking::on_initialize(deadline);
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::Disputed)
+        );
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentIsDisputed
+        );
+
+        // Consignee confirms the delivery once the dispute is settled
+        assert_ok!(ProductTracking::confirm_delivery(
+            Origin::signed(owner),
+            shipment_id.clone()
+        ));
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::Delivered)
+        );
+    })
+}
+
+#[test]
+fn initiate_return_of_disputed_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let original_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let return_id = b"R0001".to_vec();
+        let now = 42;
+
+        // Store shipment w/ Disputed status, consigned to its owner
+        store_test_shipment::<Test>(
+            original_id.clone(),
+            owner,
+            ShipmentStatus::Disputed,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Refused goods can be returned without confirming their delivery
+        assert_ok!(ProductTracking::initiate_return(
+            Origin::signed(owner),
+            original_id.clone(),
+            return_id.clone(),
+            owner
+        ));
+        let original = ProductTracking::shipment_by_id(&original_id).unwrap();
+        assert_eq!(original.status, ShipmentStatus::Disputed);
+        assert_eq!(original.returned_as, Some(return_id.clone()));
+        assert_eq!(
+            ProductTracking::shipment_by_id(&return_id).map(|s| s.return_of),
+            Some(Some(original_id.clone()))
+        );
+
+        // Returned shipment can't be returned again, nor its delivery confirmed
+        assert_noop!(
+            ProductTracking::initiate_return(
+                Origin::signed(owner),
+                original_id.clone(),
+                b"R0002".to_vec(),
+                owner
+            ),
+            Error::<Test>::ShipmentHasBeenReturned
+        );
+        assert_noop!(
+            ProductTracking::confirm_delivery(Origin::signed(owner), original_id.clone()),
+            Error::<Test>::ShipmentHasBeenReturned
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_invalid_quantity() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        store_test_product::<Test>(TEST_PRODUCT_ID.as_bytes().to_owned(), owner);
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                owner,
+                account_key(TEST_CONSIGNEE),
+                vec![test_line(TEST_PRODUCT_ID, 0)]
+            ),
+            Error::<Test>::ShipmentHasInvalidQuantity
+        );
+    })
+}
+
+#[test]
+fn track_shipment_partial_delivery() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![
+                test_line("00012345600001", 40),
+                test_line("00012345600002", 12),
+            ],
+            now,
+        );
+
+        // Delivery note must match the shipment's lines
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None,
+                Some(DeliveryNote {
+                    quantities: vec![40],
+                    override_expiry: false
+                }),
+                None
+            ),
+            Error::<Test>::InvalidDeliveryNote
+        );
+
+        // Shipment is delivered with a missing pallet
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            Some(DeliveryNote {
+                quantities: vec![40, 11],
+                override_expiry: false
+            }),
+            None
+        ));
+
+        let shipment = ProductTracking::shipment_by_id(&shipment_id).unwrap();
+        assert_eq!(shipment.delivered_quantities, vec![40, 11]);
+        assert_eq!(shipment.fulfilment, Some(Fulfilment::ShortShipped));
+    })
+}
+
+#[test]
+fn register_shipment_with_lots() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        // GS1 lot numbers are up to 20 characters long
+        let long_lot = b"LOT-2020-11-0001-ABC".to_vec();
+        store_test_product::<Test>(TEST_PRODUCT_ID.as_bytes().to_owned(), owner);
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                id.clone(),
+                owner,
+                account_key(TEST_CONSIGNEE),
+                vec![ShipmentLine {
+                    lot: Some(b"LOT-2020-11-0001-ABCD".to_vec()),
+                    ..test_line(TEST_PRODUCT_ID, 10)
+                }]
+            ),
+            Error::<Test>::InvalidOrMissingIdentifier
+        );
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(account_key(TEST_SENDER)),
+            id.clone(),
+            owner,
+            account_key(TEST_CONSIGNEE),
+            vec![
+                ShipmentLine {
+                    lot: Some(b"L001".to_vec()),
+                    expiry: Some(1000),
+                    ..test_line(TEST_PRODUCT_ID, 10)
+                },
+                ShipmentLine {
+                    lot: Some(long_lot.clone()),
+                    expiry: Some(2000),
+                    ..test_line(TEST_PRODUCT_ID, 10)
+                },
+            ]
+        ));
+
+        // Lots are indexed
+        assert_eq!(
+            ProductTracking::shipments_of_lot(
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                b"L001".to_vec()
+            ),
+            vec![id.clone()]
+        );
+        assert_eq!(
+            ProductTracking::shipments_of_lot(TEST_PRODUCT_ID.as_bytes().to_owned(), long_lot),
+            vec![id.clone()]
+        );
+    })
+}
+
+#[test]
+fn track_shipment_delivery_of_expired_products() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status, with a lot expiring before delivery
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![ShipmentLine {
+                lot: Some(b"L001".to_vec()),
+                expiry: Some(now - 1),
+                ..test_line(TEST_PRODUCT_ID, 10)
+            }],
+            now,
+        );
+
+        // Delivery of expired products is refused
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentHasExpiredProducts
+        );
+
+        // Unless explicitly overridden
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            Some(DeliveryNote {
+                quantities: vec![10],
+                override_expiry: true
+            }),
+            None
+        ));
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.status),
+            Some(ShipmentStatus::AwaitingConfirmation)
+        );
+    })
+}
+
+#[test]
+fn recall_product_lot() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let lot = b"L001".to_vec();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+        store_test_product::<Test>(product_id.clone(), owner);
+
+        // Two shipments carry the recalled lot, one carries another lot
+        for (id, line_lot) in [(b"0001", b"L001"), (b"0002", b"L001"), (b"0003", b"L002")].iter() {
+            assert_ok!(ProductTracking::register_shipment(
+                Origin::signed(owner),
+                id.to_vec(),
+                owner,
+                account_key(TEST_CONSIGNEE),
+                vec![ShipmentLine {
+                    lot: Some(line_lot.to_vec()),
+                    ..test_line(TEST_PRODUCT_ID, 10)
+                }]
+            ));
+        }
+
+        // Lot IDs must be valid
+        assert_noop!(
+            ProductTracking::recall(Origin::root(), product_id.clone(), Some(vec![])),
+            Error::<Test>::InvalidOrMissingIdentifier
+        );
+
+        // Only the recall origin can recall a product
+        assert_noop!(
+            ProductTracking::recall(Origin::signed(owner), product_id.clone(), Some(lot.clone())),
+            dispatch::DispatchError::BadOrigin
+        );
+        assert_ok!(ProductTracking::recall(
+            Origin::root(),
+            product_id.clone(),
+            Some(lot.clone())
+        ));
+
+        // Affected shipments are flagged & have a recall event
+        assert_eq!(
+            ProductTracking::recall_of((product_id.clone(), Some(lot.clone()))),
+            Some(now)
+        );
+        for id in [b"0001".to_vec(), b"0002".to_vec()].iter() {
+            assert_eq!(
+                ProductTracking::shipment_by_id(id).map(|s| s.recalled),
+                Some(true)
+            );
+            let last_event = *ProductTracking::events_of_shipment(id).last().unwrap();
+            assert_eq!(
+                AllEvents::<Test>::get(last_event).map(|ev| ev.event_type),
+                Some(ShippingEventType::ShipmentRecall)
+            );
+        }
+        assert_eq!(
+            ProductTracking::shipment_by_id(b"0003".to_vec()).map(|s| s.recalled),
+            Some(false)
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ProductRecalled(
+                product_id.clone(),
+                Some(lot.clone()),
+                vec![b"0001".to_vec(), b"0002".to_vec()]
+            ))));
+
+        // Recalled shipment cannot be delivered
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                b"0001".to_vec(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentIsRecalled
+        );
+
+        // Once the recall is cleared, shipment can be delivered again
+        assert_ok!(ProductTracking::clear_recall(
+            Origin::root(),
+            product_id.clone(),
+            Some(lot.clone())
+        ));
+        assert_eq!(
+            ProductTracking::recall_of((product_id.clone(), Some(lot.clone()))),
+            None
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::RecallCleared(
+                product_id.clone(),
+                Some(lot.clone()),
+                vec![b"0001".to_vec(), b"0002".to_vec()]
+            ))));
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            b"0001".to_vec(),
+            ShippingOperation::Deliver,
+            now,
+            None,
+            None,
+            None,
+            None
+        ));
+    })
+}
+
+#[test]
+fn monitor_shipment_with_excursion() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let now = 42;
+        store_test_product::<Test>(product_id.clone(), owner);
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
+
+        // Invalid rules are rejected
+        assert_noop!(
+            ProductTracking::set_shipment_rules(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                vec![ThresholdRule {
+                    reading_type: ReadingType::Temperature,
+                    min: Some(I16F16::from_num(8)),
+                    max: Some(I16F16::from_num(2)),
+                }]
+            ),
+            Error::<Test>::InvalidThresholdRule
+        );
+
+        // Rules can only be set by the shipment's owner or custodian, & the product's owner
+        let rule = ThresholdRule {
+            reading_type: ReadingType::Temperature,
+            min: None,
+            max: Some(I16F16::from_num(30)),
+        };
+        assert_noop!(
+            ProductTracking::set_shipment_rules(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone(),
+                vec![rule.clone()]
+            ),
+            Error::<Test>::SenderIsNotOwnerOrCustodian
+        );
+        assert_noop!(
+            ProductTracking::set_product_rules(
+                Origin::signed(account_key(TEST_CARRIER)),
+                product_id.clone(),
+                vec![rule]
+            ),
+            Error::<Test>::ProductIsNotOwnedByOrganization
+        );
+
+        // Temperature must stay between 2 and 8 for the shipment,
+        // & the product must not be shocked above 3g
+        assert_ok!(ProductTracking::set_shipment_rules(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            vec![ThresholdRule {
+                reading_type: ReadingType::Temperature,
+                min: Some(I16F16::from_num(2)),
+                max: Some(I16F16::from_num(8)),
+            }]
+        ));
+        assert_ok!(ProductTracking::set_product_rules(
+            Origin::signed(owner),
+            product_id.clone(),
+            vec![ThresholdRule {
+                reading_type: ReadingType::Shock,
+                min: None,
+                max: Some(I16F16::from_num(3)),
+            }]
+        ));
+
+        let readings = vec![
+            Reading {
+                device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+                reading_type: ReadingType::Temperature,
+                value: I16F16::from_num(5),
+                timestamp: now,
+                unit: ReadingUnit::Celsius,
+                signature: None,
+            },
+            Reading {
+                device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+                reading_type: ReadingType::Temperature,
+                value: I16F16::from_num(9.5),
+                timestamp: now,
+                unit: ReadingUnit::Celsius,
+                signature: None,
+            },
+            Reading {
+                device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+                reading_type: ReadingType::Shock,
+                value: I16F16::from_num(1),
+                timestamp: now,
+                unit: ReadingUnit::StandardGravity,
+                signature: None,
+            },
+        ];
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            None,
+            Some(readings),
+            None,
+            None
+        ));
+
+        // Excursion is logged & shipment is marked as non-compliant
+        assert_eq!(
+            ProductTracking::excursions_of_shipment(&shipment_id),
+            vec![Excursion {
+                event_idx: 1,
+                device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+                reading_type: ReadingType::Temperature,
+                value: I16F16::from_num(9.5),
+                timestamp: now,
+            }]
+        );
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|s| s.compliant),
+            Some(false)
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ExcursionDetected(
+                shipment_id.clone(),
+                1,
+                ReadingType::Temperature,
+                I16F16::from_num(9.5)
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_delivery_issues_compliance_certificate() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let temperature = |value: f64, timestamp: u64| Reading {
+            device_id: device_id.clone(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(value),
+            timestamp,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            0,
+        );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
+
+        // Temperature must not exceed 8
+        assert_ok!(ProductTracking::set_shipment_rules(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            vec![ThresholdRule {
+                reading_type: ReadingType::Temperature,
+                min: None,
+                max: Some(I16F16::from_num(8)),
+            }]
+        ));
+
+        // Temperature goes out of range between 20 & 30
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            20,
+            None,
+            Some(vec![temperature(5.0, 10), temperature(9.5, 20)]),
+            None,
+            None
+        ));
+        assert_eq!(ProductTracking::certificate_of(&shipment_id), None);
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Deliver,
+            50,
+            None,
+            Some(vec![temperature(6.0, 30)]),
+            None,
+            None
+        ));
+
+        // Certificate summarizes all readings of the shipment
+        let certificate = ProductTracking::certificate_of(&shipment_id).unwrap();
+        assert_eq!(certificate.shipment_id, shipment_id);
+        assert_eq!(certificate.compliant, false);
+        assert_eq!(certificate.excursions, 1);
+        assert_eq!(certificate.issued, 50);
+        assert_eq!(certificate.summaries.len(), 1);
+
+        let summary = &certificate.summaries[0];
+        assert_eq!(summary.reading_type, ReadingType::Temperature);
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.min, I16F16::from_num(5));
+        assert_eq!(summary.max, I16F16::from_num(9.5));
+        assert!((summary.mean - I16F16::from_num(6.8333)).abs() < I16F16::from_num(0.001));
+        assert_eq!(summary.time_out_of_range, 10);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ComplianceCertificateIssued(
+                shipment_id.clone(),
+                false
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_updates_reading_aggregates() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let reading = |reading_type: ReadingType, value: f64, timestamp: u64| Reading {
+            device_id: device_id.clone(),
+            unit: reading_type.dimension().unwrap().canonical_unit(),
+            reading_type,
+            value: I16F16::from_num(value),
+            timestamp,
+            signature: None,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            0,
+        );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
+
+        // Dispatchable calls succeed
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            20,
+            None,
+            Some(vec![
+                reading(ReadingType::Temperature, 5.5, 10),
+                reading(ReadingType::Humidity, 60.0, 10),
+                reading(ReadingType::Temperature, 7.0, 20),
+            ]),
+            None,
+            None
+        ));
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            30,
+            None,
+            Some(vec![reading(ReadingType::Temperature, -1.5, 30)]),
+            None,
+            None
+        ));
+
+        // Aggregates are kept per reading type
+        assert_eq!(
+            ProductTracking::reading_aggregate(&shipment_id, ReadingType::Temperature),
+            Some(ReadingAggregate {
+                count: 3,
+                min: I16F16::from_num(-1.5),
+                max: I16F16::from_num(7),
+                sum: WideDecimal::from_num(11),
+                first: 10,
+                last: 30,
+            })
+        );
+        assert_eq!(
+            ProductTracking::reading_aggregate(&shipment_id, ReadingType::Humidity)
+                .map(|aggregate| aggregate.mean()),
+            Some(I16F16::from_num(60))
+        );
+        assert_eq!(
+            ProductTracking::reading_aggregate(&shipment_id, ReadingType::Shock),
+            None
+        );
+    })
+}
+
+#[test]
+fn register_and_attach_device() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let sender = account_key(TEST_SENDER);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(sender),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            None
+        ));
+
+        // Storage is correctly updated
+        assert_eq!(
+            ProductTracking::device_by_id(&device_id),
+            Some(Device {
+                id: device_id.clone(),
+                owner,
+                reading_types: vec![ReadingType::Temperature],
+                public_key: None,
+                status: DeviceStatus::Active,
+                registered: now,
+            })
+        );
+        assert_eq!(
+            ProductTracking::devices_of_org(owner),
+            vec![device_id.clone()]
+        );
+
+        // Device can't be registered twice
+        assert_noop!(
+            ProductTracking::register_device(
+                Origin::signed(sender),
+                device_id.clone(),
+                owner,
+                vec![ReadingType::Temperature],
+                None
+            ),
+            Error::<Test>::DeviceAlreadyExists
+        );
+
+        // Only the shipment's custodian can attach a device
+        assert_noop!(
+            ProductTracking::attach_device(
+                Origin::signed(sender),
+                shipment_id.clone(),
+                device_id.clone()
+            ),
+            Error::<Test>::SenderIsNotCustodian
+        );
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+        assert_eq!(
+            ProductTracking::devices_of_shipment(&shipment_id),
+            vec![device_id.clone()]
+        );
+        assert_noop!(
+            ProductTracking::attach_device(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                device_id.clone()
+            ),
+            Error::<Test>::DeviceIsAlreadyAttached
+        );
+
+        // Events are raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeviceRegistered(
+                sender,
+                device_id.clone(),
+                owner
+            ))));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeviceAttached(
+                owner,
+                shipment_id.clone(),
+                device_id.clone()
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_with_invalid_device_readings() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let now = 42;
+        let reading = |device_id: &[u8], reading_type: ReadingType| Reading {
+            device_id: device_id.to_vec(),
+            unit: reading_type.dimension().unwrap().canonical_unit(),
+            reading_type,
+            value: I16F16::from_num(5),
+            timestamp: now,
+            signature: None,
+        };
+        let track = |reading: Reading<u64>| {
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                Some(vec![reading]),
+                None,
+                None,
+            )
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Unknown device
+        assert_noop!(
+            track(reading(&device_id, ReadingType::Temperature)),
+            Error::<Test>::DeviceIsUnknown
+        );
+
+        // Device not attached to the shipment
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            None
+        ));
+        assert_noop!(
+            track(reading(&device_id, ReadingType::Temperature)),
+            Error::<Test>::DeviceIsNotAttachedToShipment
+        );
+
+        // Reading type not supported by the device
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+        assert_noop!(
+            track(reading(&device_id, ReadingType::Shock)),
+            Error::<Test>::DeviceDoesNotSupportReadingType
+        );
+        assert_ok!(track(reading(&device_id, ReadingType::Temperature)));
+
+        // Decommissioned device
+        assert_ok!(ProductTracking::decommission_device(
+            Origin::signed(owner),
+            device_id.clone()
+        ));
+        assert_noop!(
+            track(reading(&device_id, ReadingType::Temperature)),
+            Error::<Test>::DeviceIsDecommissioned
+        );
+    })
+}
+
+#[test]
+fn track_shipment_with_signed_readings() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let device = device_pair(TEST_DEVICE_ID);
+        let now = 42;
+        let reading = |value: f64| Reading {
+            device_id: device_id.clone(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(value),
+            timestamp: now,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Register device w/ its public key & attach it to the shipment
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            Some(device.public())
+        ));
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+
+        // Device signs the first reading only
+        let mut signed = reading(5.5);
+        signed.signature = Some(device.sign(&signed.signing_payload(&shipment_id)));
+        let unsigned = reading(6.0);
+
+        // Readings signed for another shipment are rejected
+        let mut replayed = signed.clone();
+        replayed.signature = Some(device.sign(&replayed.signing_payload(b"0002")));
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                Some(vec![replayed]),
+                None,
+                None
+            ),
+            Error::<Test>::ReadingSignatureIsInvalid
+        );
+
+        // Tampered readings are rejected
+        let mut tampered = signed.clone();
+        tampered.value = I16F16::from_num(4);
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                Some(vec![tampered]),
+                None,
+                None
+            ),
+            Error::<Test>::ReadingSignatureIsInvalid
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            None,
+            Some(vec![signed, unsigned]),
+            None,
+            None
+        ));
+
+        // Only the signed reading is flagged as verified
+        assert_eq!(
+            ProductTracking::event_by_idx(1).map(|event| event.annotations),
+            Some(vec![
+                ReadingAnnotation {
+                    verified: true,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(5.5),
+                },
+                ReadingAnnotation {
+                    verified: false,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(6),
+                }
+            ])
+        );
+    })
+}
+
+#[test]
+fn submit_readings_unsigned() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let device = device_pair(TEST_DEVICE_ID);
+        let now = 42;
+
+        // Store shipment w/ InTransit status & attach a device to it
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            Some(device.public())
+        ));
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+
+        // Device signs a payload of readings
+        let readings = vec![Reading {
+            device_id: device_id.clone(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(5),
+            timestamp: now,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        }];
+        let payload = ReadingsPayload {
+            signer: device_id.clone(),
+            shipment_id: shipment_id.clone(),
+            nonce: 0,
+            timestamp: now,
+            location: None,
+            readings: readings.clone(),
+        };
+        let signature = device.sign(&payload.encode());
+
+        // Transaction is valid
+        let call = Call::submit_readings_unsigned(payload.clone(), signature.clone());
+        let tx = ProductTracking::validate_unsigned(TransactionSource::External, &call).unwrap();
+        assert_eq!(tx.priority, 100);
+        assert_eq!(
+            tx.provides,
+            vec![("ProductTracking", (device_id.clone(), 0u64)).encode()]
+        );
+        assert_eq!(tx.requires, Vec::<Vec<u8>>::new());
+        assert_eq!(tx.longevity, UNSIGNED_READINGS_LONGEVITY);
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::submit_readings_unsigned(
+            Origin::none(),
+            payload.clone(),
+            signature.clone()
+        ));
+
+        // Same shipping event as `track_shipment`
+        assert_eq!(
+            ProductTracking::event_by_idx(1),
+            Some(ShippingEvent {
+                event_type: ShippingEventType::ShipmentScan,
+                shipment_id: shipment_id.clone(),
+                location: None,
+                facility: None,
+                readings,
+                annotations: vec![ReadingAnnotation {
+                    verified: false,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(5),
+                }],
+                timestamp: now,
+            })
+        );
+        assert_eq!(ProductTracking::device_nonce(&device_id), 1);
+
+        // Payload can't be replayed
+        assert_eq!(
+            ProductTracking::validate_unsigned(TransactionSource::External, &call),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Stale))
+        );
+        assert_noop!(
+            ProductTracking::submit_readings_unsigned(Origin::none(), payload, signature),
+            Error::<Test>::ReadingsPayloadNonceIsInvalid
+        );
+    })
+}
+
+#[test]
+fn submit_readings_unsigned_with_invalid_signature() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            Some(device_pair(TEST_DEVICE_ID).public())
+        ));
+
+        // Payload is signed by another key
+        let payload = ReadingsPayload {
+            signer: device_id,
+            shipment_id: TEST_SHIPMENT_ID.as_bytes().to_owned(),
+            nonce: 0,
+            timestamp: 42,
+            location: None,
+            readings: vec![],
+        };
+        let signature = device_pair(TEST_SENDER).sign(&payload.encode());
+
+        let call = Call::submit_readings_unsigned(payload.clone(), signature.clone());
+        assert_eq!(
+            ProductTracking::validate_unsigned(TransactionSource::External, &call),
+            Err(TransactionValidityError::Invalid(
+                InvalidTransaction::BadProof
+            ))
+        );
+        assert_noop!(
+            ProductTracking::submit_readings_unsigned(Origin::none(), payload, signature),
+            Error::<Test>::ReadingsPayloadSignatureIsInvalid
+        );
+    })
+}
+
+#[test]
+fn submit_readings_unsigned_with_invalid_payload() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let other_id = b"other-sensor".to_vec();
+        let device = device_pair(TEST_DEVICE_ID);
+        let now = 42;
+
+        // Store shipment w/ InTransit status & another device attached to it
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+        store_test_device::<Test>(other_id.clone(), owner, &shipment_id);
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature],
+            Some(device.public())
+        ));
+
+        let submit = |readings: Vec<Reading<u64>>| {
+            let payload = ReadingsPayload {
+                signer: device_id.clone(),
+                shipment_id: shipment_id.clone(),
+                nonce: 0,
+                timestamp: now,
+                location: None,
+                readings,
+            };
+            let signature = device.sign(&payload.encode());
+            let call = Call::submit_readings_unsigned(payload.clone(), signature.clone());
+            assert_eq!(
+                ProductTracking::validate_unsigned(TransactionSource::External, &call),
+                Err(TransactionValidityError::Invalid(
+                    InvalidTransaction::BadProof
+                ))
+            );
+            ProductTracking::submit_readings_unsigned(Origin::none(), payload, signature)
+        };
+
+        // Signer isn't attached to the shipment
+        assert_noop!(submit(vec![]), Error::<Test>::DeviceIsNotAttachedToShipment);
+
+        // Signer can't pass off unsigned readings as another device's
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+        assert_noop!(
+            submit(vec![Reading {
+                device_id: other_id,
+                reading_type: ReadingType::Temperature,
+                value: I16F16::from_num(5),
+                timestamp: now,
+                unit: ReadingUnit::Celsius,
+                signature: None,
+            }]),
+            Error::<Test>::ReadingSignatureIsInvalid
+        );
+
+        // Payloads can't carry more than PAYLOAD_MAX_READINGS readings
+        let reading = Reading {
+            device_id: device_id.clone(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(5),
+            timestamp: now,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+        assert_noop!(
+            submit(vec![reading; PAYLOAD_MAX_READINGS + 1]),
+            Error::<Test>::ReadingsPayloadHasTooManyReadings
+        );
+    })
+}
+
+#[test]
+fn track_shipment_with_calibrated_device() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let reading = |timestamp: u64| Reading {
+            device_id: device_id.clone(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(4),
+            timestamp,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+        let calibration = |calibrated: u64, valid_until: u64| Calibration {
+            calibrated,
+            valid_until,
+            offset: I16F16::from_num(-0.5),
+            scale: I16F16::from_num(1.5),
+            certificate: H256::repeat_byte(1),
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            0,
+        );
+        store_test_device::<Test>(device_id.clone(), owner, &shipment_id);
+
+        // Calibrations must be valid & recorded in chronological order
+        assert_noop!(
+            ProductTracking::record_calibration(
+                Origin::signed(owner),
+                device_id.clone(),
+                calibration(10, 5)
+            ),
+            Error::<Test>::InvalidCalibration
+        );
+        assert_ok!(ProductTracking::record_calibration(
+            Origin::signed(owner),
+            device_id.clone(),
+            calibration(10, 50)
+        ));
+        assert_noop!(
+            ProductTracking::record_calibration(
+                Origin::signed(owner),
+                device_id.clone(),
+                calibration(5, 50)
+            ),
+            Error::<Test>::InvalidCalibration
+        );
+        assert_eq!(
+            ProductTracking::calibrations_of(&device_id),
+            vec![calibration(10, 50)]
+        );
+
+        // Readings are tagged & corrected w/ the calibration in force
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            60,
+            None,
+            Some(vec![reading(5), reading(20), reading(60)]),
+            None,
+            None
+        ));
+        assert_eq!(
+            ProductTracking::event_by_idx(1).map(|event| event.annotations),
+            Some(vec![
+                ReadingAnnotation {
+                    verified: false,
+                    calibration: None,
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(4),
+                },
+                ReadingAnnotation {
+                    verified: false,
+                    calibration: Some(0),
+                    calibration_expired: false,
+                    corrected_value: I16F16::from_num(5.5),
+                },
+                ReadingAnnotation {
+                    verified: false,
+                    calibration: Some(0),
+                    calibration_expired: true,
+                    corrected_value: I16F16::from_num(5.5),
+                }
+            ])
+        );
+        assert_eq!(
+            ProductTracking::reading_aggregate(&shipment_id, ReadingType::Temperature)
+                .map(|aggregate| aggregate.max),
+            Some(I16F16::from_num(5.5))
+        );
+
+        // Readings w/ an expired calibration can be rejected instead
+        set_calibration_policy(CalibrationPolicy::Reject);
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                70,
+                None,
+                Some(vec![reading(70)]),
+                None,
+                None
+            ),
+            Error::<Test>::DeviceCalibrationHasExpired
+        );
+        set_calibration_policy(CalibrationPolicy::Flag);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeviceCalibrated(
+                owner,
+                device_id.clone(),
+                0
+            ))));
+    })
+}
+
+#[test]
+fn anchor_readings_and_verify_inclusion() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let reading = |timestamp: u64| Reading {
+            device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(timestamp),
+            timestamp,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            0,
+        );
+
+        // Build Merkle tree of an off-chain batch of readings
+        let readings = (10..15).map(reading).collect::<Vec<_>>();
+        let leaves = readings.iter().map(merkle::leaf_hash).collect::<Vec<_>>();
+        let root = merkle::root(&leaves).unwrap();
+
+        // Invalid batches are rejected
+        assert_noop!(
+            ProductTracking::anchor_readings(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                root,
+                5,
+                14,
+                10
+            ),
+            Error::<Test>::InvalidReadingBatch
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::anchor_readings(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            root,
+            5,
+            10,
+            14
+        ));
+        assert_eq!(
+            ProductTracking::batches_of_shipment(&shipment_id),
+            vec![ReadingBatch {
+                root,
+                count: 5,
+                from: 10,
+                to: 14,
+            }]
+        );
+
+        // Every reading of the batch can be proven
+        for (i, reading) in readings.iter().enumerate() {
+            let proof = merkle::proof(&leaves, i).unwrap();
+            assert!(merkle::verify_proof(&root, &leaves[i], &proof));
+            assert!(ProductTracking::verify_anchored_reading(
+                &shipment_id,
+                0,
+                reading,
+                &proof
+            ));
+        }
+
+        // Readings outside of the batch can't
+        let proof = merkle::proof(&leaves, 0).unwrap();
+        let mut forged = readings[0].clone();
+        forged.value = I16F16::from_num(-20);
+        assert!(!ProductTracking::verify_anchored_reading(
+            &shipment_id,
+            0,
+            &forged,
+            &proof
+        ));
+        assert!(!ProductTracking::verify_anchored_reading(
+            &shipment_id,
+            1,
+            &readings[0],
+            &proof
+        ));
+        assert_eq!(merkle::proof(&leaves, 5), None);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ReadingsAnchored(
+                owner,
+                shipment_id.clone(),
+                0,
+                root
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_with_implausible_readings() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let reading = |value: i32, timestamp: u64| Reading {
+            device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+            reading_type: ReadingType::Temperature,
+            value: I16F16::from_num(value),
+            timestamp,
+            unit: ReadingUnit::Celsius,
+            signature: None,
+        };
+        let bounds = ReadingBounds {
+            min: I16F16::from_num(-100),
+            max: I16F16::from_num(100),
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            10,
+        );
+        store_test_device::<Test>(TEST_DEVICE_ID.as_bytes().to_owned(), owner, &shipment_id);
+
+        // Only the admin origin can set plausibility bounds
+        assert_noop!(
+            ProductTracking::set_plausibility_bounds(
+                Origin::signed(owner),
+                ReadingType::Temperature,
+                Some(bounds.clone())
+            ),
+            dispatch::DispatchError::BadOrigin
+        );
+        assert_ok!(ProductTracking::set_plausibility_bounds(
+            Origin::root(),
+            ReadingType::Temperature,
+            Some(bounds.clone())
+        ));
+        assert_eq!(
+            ProductTracking::plausibility_bounds(ReadingType::Temperature),
+            Some(bounds)
+        );
+
+        // Readings out of bounds are rejected
+        let shipment = ProductTracking::shipment_by_id(&shipment_id).unwrap();
+        let readings = vec![reading(5, now), reading(300, now)];
+        assert!(matches!(
+            ProductTracking::validate_readings_plausibility(&shipment, &readings, now),
+            Err((1, Error::<Test>::ReadingIsImplausible))
+        ));
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                Some(readings),
+                None,
+                None
+            ),
+            Error::<Test>::ReadingIsImplausible
+        );
+
+        // Readings taken before the shipment's registration, or after the event, are rejected
+        for timestamp in vec![5, 50] {
+            assert_noop!(
+                ProductTracking::track_shipment(
+                    Origin::signed(owner),
+                    shipment_id.clone(),
+                    ShippingOperation::Scan,
+                    now,
+                    None,
+                    Some(vec![reading(5, timestamp)]),
+                    None,
+                    None
+                ),
+                Error::<Test>::ReadingTimestampIsInvalid
+            );
+        }
+    })
+}
+
+#[test]
+fn track_shipment_with_custom_reading_types_and_units() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let co2 = ReadingType::Custom(1);
+        let now = 42;
+        let reading = |reading_type: ReadingType, value: f64, unit: ReadingUnit| Reading {
+            device_id: device_id.clone(),
+            reading_type,
+            value: I16F16::from_num(value),
+            unit,
+            timestamp: now,
+            signature: None,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Custom reading types must be registered by the admin origin
+        assert_noop!(
+            ProductTracking::register_device(
+                Origin::signed(owner),
+                device_id.clone(),
+                owner,
+                vec![ReadingType::Temperature, co2.clone()],
+                None
+            ),
+            Error::<Test>::ReadingTypeIsUnknown
+        );
+        assert_ok!(ProductTracking::register_reading_type(
+            Origin::root(),
+            1,
+            CustomReadingType {
+                name: "CO2".as_bytes().to_owned(),
+                dimension: Dimension::Concentration,
+            }
+        ));
+        assert_noop!(
+            ProductTracking::register_reading_type(
+                Origin::root(),
+                1,
+                CustomReadingType {
+                    name: "Light".as_bytes().to_owned(),
+                    dimension: Dimension::Illuminance,
+                }
+            ),
+            Error::<Test>::ReadingTypeAlreadyExists
+        );
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(owner),
+            device_id.clone(),
+            owner,
+            vec![ReadingType::Temperature, co2.clone()],
+            None
+        ));
+        assert_ok!(ProductTracking::attach_device(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            device_id.clone()
+        ));
+
+        // Units must be of the reading type's dimension
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                Some(vec![reading(co2.clone(), 400.0, ReadingUnit::Celsius)]),
+                None,
+                None
+            ),
+            Error::<Test>::ReadingUnitDoesNotMatchType
+        );
+
+        // Dispatchable call succeeds
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            None,
+            Some(vec![
+                reading(ReadingType::Temperature, 41.0, ReadingUnit::Fahrenheit),
+                reading(ReadingType::Temperature, 280.15, ReadingUnit::Kelvin),
+                reading(co2.clone(), 415.0, ReadingUnit::PartsPerMillion),
+            ]),
+            None,
+            None
+        ));
+
+        // Aggregates are kept in the canonical unit of each dimension
+        let temperature =
+            ProductTracking::reading_aggregate(&shipment_id, ReadingType::Temperature).unwrap();
+        assert_eq!(temperature.count, 2);
+        assert!((temperature.min - I16F16::from_num(5)).abs() < I16F16::from_num(0.001));
+        assert!((temperature.max - I16F16::from_num(7)).abs() < I16F16::from_num(0.001));
+        assert_eq!(
+            ProductTracking::reading_aggregate(&shipment_id, co2).map(|aggregate| aggregate.max),
+            Some(I16F16::from_num(415))
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ReadingTypeRegistered(
+                1,
+                Dimension::Concentration
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_with_invalid_location() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let location = |latitude: f64, longitude: f64, accuracy: i32| ReadPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+            altitude: None,
+            accuracy: Some(I16F16::from_num(accuracy)),
+            source: LocationSource::Gps,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Out-of-range coordinates or a negative accuracy are rejected
+        for invalid in vec![
+            location(91.0, 13.4355606, 5),
+            location(52.4941126, -181.0, 5),
+            location(52.4941126, 13.4355606, -1),
+        ] {
+            assert_noop!(
+                ProductTracking::track_shipment(
+                    Origin::signed(owner),
+                    shipment_id.clone(),
+                    ShippingOperation::Scan,
+                    now,
+                    Some(invalid),
+                    None,
+                    None,
+                    None
+                ),
+                Error::<Test>::InvalidLocation
+            );
+        }
+
+        // Coordinates keep their sub-metre precision
+        let precise = location(52.49411264, 13.43556061, 5);
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Scan,
+            now,
+            Some(precise.clone()),
+            None,
+            None,
+            None
+        ));
+        let event_idx = *ProductTracking::events_of_shipment(&shipment_id)
+            .last()
+            .unwrap();
+        let event = ProductTracking::event_by_idx(event_idx).unwrap();
+        assert_eq!(event.location, Some(precise));
+    })
+}
+
+#[test]
+fn register_facilities_and_resolve_them_from_locations() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let point = |latitude: f64, longitude: f64| GeoPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+        };
+        let location = |latitude: f64, longitude: f64| ReadPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+            altitude: None,
+            accuracy: None,
+            source: LocationSource::Gps,
+        };
+        // Rotterdam distribution centre, fenced by a polygon
+        let rotterdam = b"8712345000004".to_vec();
+        let rotterdam_fence = Geofence::Polygon(vec![
+            point(51.92, 4.47),
+            point(51.92, 4.49),
+            point(51.93, 4.49),
+            point(51.93, 4.47),
+        ]);
+        // Amsterdam warehouse, fenced by a 1km radius
+        let amsterdam = b"0614141000005".to_vec();
+        let amsterdam_fence = Geofence::Radius(point(52.3676, 4.9041), 1_000);
+
+        // Great-circle distance between both cities is ~57km
+        let distance = geo::distance(&point(51.9244, 4.4777), &point(52.3676, 4.9041)).unwrap();
+        assert!(distance > 56_000 && distance < 59_000);
+
+        // Facilities must have a valid GLN & geofence
+        assert_noop!(
+            ProductTracking::register_facility(
+                Origin::signed(owner),
+                b"8712345000005".to_vec(),
+                owner,
+                H256::zero(),
+                rotterdam_fence.clone()
+            ),
+            Error::<Test>::InvalidOrMissingIdentifier
+        );
+        assert_noop!(
+            ProductTracking::register_facility(
+                Origin::signed(owner),
+                rotterdam.clone(),
+                owner,
+                H256::zero(),
+                Geofence::Polygon(vec![point(51.92, 4.47), point(51.93, 4.49)])
+            ),
+            Error::<Test>::InvalidGeofence
+        );
+
+        assert_ok!(ProductTracking::register_facility(
+            Origin::signed(owner),
+            rotterdam.clone(),
+            owner,
+            H256::repeat_byte(1),
+            rotterdam_fence.clone()
+        ));
+        assert_ok!(ProductTracking::register_facility(
+            Origin::signed(owner),
+            amsterdam.clone(),
+            owner,
+            H256::repeat_byte(2),
+            amsterdam_fence
+        ));
+        assert_noop!(
+            ProductTracking::register_facility(
+                Origin::signed(owner),
+                rotterdam.clone(),
+                owner,
+                H256::zero(),
+                rotterdam_fence.clone()
+            ),
+            Error::<Test>::FacilityAlreadyExists
+        );
+        assert_eq!(
+            ProductTracking::facility_by_id(&rotterdam),
+            Some(Facility {
+                id: rotterdam.clone(),
+                owner,
+                address_hash: H256::repeat_byte(1),
+                geofence: rotterdam_fence,
+                registered: 0,
+            })
+        );
+        assert_eq!(
+            ProductTracking::facilities_of_org(owner),
+            vec![rotterdam.clone(), amsterdam.clone()]
+        );
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Explicit facilities must be known, & contain the event's location
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                None,
+                None,
+                None,
+                Some(b"4012345000009".to_vec())
+            ),
+            Error::<Test>::FacilityIsUnknown
+        );
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                Some(location(52.3676, 4.9041)),
+                None,
+                None,
+                Some(rotterdam.clone())
+            ),
+            Error::<Test>::LocationIsOutsideFacility
+        );
+
+        // Facilities are resolved from the events' locations, if any
+        let track = |location: ReadPoint| {
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                Some(location),
+                None,
+                None,
+                None
+            ));
+            let event_idx = *ProductTracking::events_of_shipment(&shipment_id)
+                .last()
+                .unwrap();
+            ProductTracking::event_by_idx(event_idx).unwrap().facility
+        };
+        assert_eq!(track(location(51.9244, 4.4777)), Some(rotterdam.clone()));
+        assert_eq!(track(location(52.3700, 4.9000)), Some(amsterdam.clone()));
+        assert_eq!(track(location(52.0907, 5.1214)), None);
+
+        // Facilities are indexed by the grid cells their geofence overlaps
+        assert_eq!(
+            ProductTracking::facilities_of_cell((519, 44)),
+            vec![rotterdam.clone()]
+        );
+        assert_eq!(
+            ProductTracking::facilities_of_cell((523, 49)),
+            vec![amsterdam.clone()]
+        );
+
+        // Of overlapping facilities, the one with the smallest geofence wins
+        let terminal = b"4012345000009".to_vec();
+        assert_ok!(ProductTracking::register_facility(
+            Origin::signed(owner),
+            terminal.clone(),
+            owner,
+            H256::repeat_byte(3),
+            Geofence::Radius(point(51.925, 4.475), 200)
+        ));
+        assert_eq!(track(location(51.9251, 4.4751)), Some(terminal));
+        assert_eq!(track(location(51.9244, 4.4850)), Some(rotterdam));
+    })
+}
+
+#[test]
+fn register_facility_with_bounded_geofence() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let facility = b"8712345000004".to_vec();
+        let centre = GeoPoint {
+            latitude: Coordinate::from_num(51.9244),
+            longitude: Coordinate::from_num(4.4777),
+        };
+
+        // Geofences can't overlap more than FACILITY_MAX_CELLS grid cells
+        assert_noop!(
+            ProductTracking::register_facility(
+                Origin::signed(owner),
+                facility.clone(),
+                owner,
+                H256::zero(),
+                Geofence::Radius(centre.clone(), 50_000)
+            ),
+            Error::<Test>::GeofenceIsTooLarge
+        );
+
+        // Grid cells can't be overlapped by more than CELL_MAX_FACILITIES facilities
+        let cell = geo::cell_of(&centre);
+        FacilitiesOfCell::insert(cell, vec![b"0614141000005".to_vec(); CELL_MAX_FACILITIES]);
+        assert_noop!(
+            ProductTracking::register_facility(
+                Origin::signed(owner),
+                facility.clone(),
+                owner,
+                H256::zero(),
+                Geofence::Radius(centre.clone(), 1_000)
+            ),
+            Error::<Test>::AreaHasTooManyFacilities
+        );
+        FacilitiesOfCell::remove(cell);
+        assert_ok!(ProductTracking::register_facility(
+            Origin::signed(owner),
+            facility.clone(),
+            owner,
+            H256::zero(),
+            Geofence::Radius(centre, 1_000)
+        ));
+        assert_eq!(ProductTracking::facilities_of_cell(cell), vec![facility]);
+    })
+}
+
+#[test]
+fn track_shipment_across_facility_geofences() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let point = |latitude: f64, longitude: f64| GeoPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+        };
+        let rotterdam = b"8712345000004".to_vec();
+        let amsterdam = b"0614141000005".to_vec();
+        store_test_facility::<Test>(
+            rotterdam.clone(),
+            owner,
+            Geofence::Radius(point(51.9244, 4.4777), 1_000),
+        );
+        store_test_facility::<Test>(
+            amsterdam.clone(),
+            owner,
+            Geofence::Radius(point(52.3676, 4.9041), 1_000),
+        );
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Scan the shipment at the given coordinates, & return the new events' types & facilities
+        let scan = |location: Option<GeoPoint>| {
+            let count = ProductTracking::events_of_shipment(&shipment_id).len();
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                location.map(|point| ReadPoint {
+                    latitude: point.latitude,
+                    longitude: point.longitude,
+                    altitude: None,
+                    accuracy: None,
+                    source: LocationSource::Gps,
+                }),
+                None,
+                None,
+                None
+            ));
+            ProductTracking::events_of_shipment(&shipment_id)[count..]
+                .iter()
+                .map(|idx| {
+                    let event = ProductTracking::event_by_idx(idx).unwrap();
+                    (event.event_type, event.facility)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Outside of any geofence
+        assert_eq!(
+            scan(Some(point(52.0907, 5.1214))),
+            vec![(ShippingEventType::ShipmentScan, None)]
+        );
+        // Entering Rotterdam's geofence
+        assert_eq!(
+            scan(Some(point(51.9250, 4.4780))),
+            vec![
+                (ShippingEventType::ShipmentScan, Some(rotterdam.clone())),
+                (ShippingEventType::ShipmentArrival, Some(rotterdam.clone())),
+            ]
+        );
+        // Still within Rotterdam's geofence, or without location
+        assert_eq!(
+            scan(Some(point(51.9240, 4.4770))),
+            vec![(ShippingEventType::ShipmentScan, Some(rotterdam.clone()))]
+        );
+        assert_eq!(scan(None), vec![(ShippingEventType::ShipmentScan, None)]);
+        // Straight from Rotterdam to Amsterdam
+        assert_eq!(
+            scan(Some(point(52.3680, 4.9040))),
+            vec![
+                (ShippingEventType::ShipmentScan, Some(amsterdam.clone())),
+                (ShippingEventType::ShipmentDeparture, Some(rotterdam)),
+                (ShippingEventType::ShipmentArrival, Some(amsterdam.clone())),
+            ]
+        );
+        // Leaving Amsterdam's geofence
+        assert_eq!(
+            scan(Some(point(52.0907, 5.1214))),
+            vec![
+                (ShippingEventType::ShipmentScan, None),
+                (ShippingEventType::ShipmentDeparture, Some(amsterdam)),
+            ]
+        );
+        assert_eq!(
+            ProductTracking::last_position_of(&shipment_id),
+            Some(LastPosition {
+                point: point(52.0907, 5.1214),
+                facility: None,
+                timestamp: now,
+            })
+        );
+    })
+}
+
+#[test]
+fn track_shipment_off_planned_route() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let point = |latitude: f64, longitude: f64| GeoPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+        };
+        // Rotterdam to Amsterdam, via Utrecht
+        let route = Route {
+            waypoints: vec![
+                point(51.9244, 4.4777),
+                point(52.0907, 5.1214),
+                point(52.3676, 4.9041),
+            ],
+            corridor: 2_000,
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Routes must have waypoints & a corridor
+        for invalid in vec![
+            Route {
+                waypoints: vec![],
+                corridor: 2_000,
+            },
+            Route {
+                waypoints: route.waypoints.clone(),
+                corridor: 0,
+            },
+        ] {
+            assert_noop!(
+                ProductTracking::set_shipment_route(
+                    Origin::signed(owner),
+                    shipment_id.clone(),
+                    Some(invalid)
+                ),
+                Error::<Test>::InvalidRoute
+            );
+        }
+
+        // Only the shipment's owner or custodian can set its route
+        assert_noop!(
+            ProductTracking::set_shipment_route(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone(),
+                Some(route.clone())
+            ),
+            Error::<Test>::SenderIsNotOwnerOrCustodian
+        );
+        assert_ok!(ProductTracking::set_shipment_route(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            Some(route.clone())
+        ));
+        assert_eq!(ProductTracking::shipment_route(&shipment_id), Some(route));
+
+        let scan = |point: GeoPoint| {
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                now,
+                Some(ReadPoint {
+                    latitude: point.latitude,
+                    longitude: point.longitude,
+                    altitude: None,
+                    accuracy: None,
+                    source: LocationSource::Gps,
+                }),
+                None,
+                None,
+                None
+            ));
+            ProductTracking::shipment_by_id(&shipment_id)
+                .unwrap()
+                .deviations
+        };
+
+        // Locations within the corridor, between & at the waypoints
+        assert_eq!(scan(point(52.0076, 4.7996)), 0);
+        assert_eq!(scan(point(52.0907, 5.1214)), 0);
+        assert_eq!(scan(point(52.2300, 5.0100)), 0);
+
+        // Location in Eindhoven, way off the route
+        assert_eq!(scan(point(51.4416, 5.4697)), 1);
+        assert!(System::events().iter().any(|er| matches!(
+            er.event,
+            TestEvent::product_tracking(RawEvent::RouteDeviation(_, _, distance))
+                if distance > 50_000
+        )));
+    })
+}
+
+#[test]
+fn track_shipment_distance_and_emissions() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        let point = |latitude: f64, longitude: f64| GeoPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+        };
+        let (rotterdam, utrecht, amsterdam) = (
+            point(51.9244, 4.4777),
+            point(52.0907, 5.1214),
+            point(52.3676, 4.9041),
+        );
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            now,
+        );
+
+        // Only the shipment's owner or custodian can set its transport mode
+        assert_noop!(
+            ProductTracking::set_transport_mode(
+                Origin::signed(account_key(TEST_CARRIER)),
+                shipment_id.clone(),
+                TransportMode::Air
+            ),
+            Error::<Test>::SenderIsNotOwnerOrCustodian
+        );
+        assert_ok!(ProductTracking::set_transport_mode(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            TransportMode::Road
+        ));
+
+        let track = |operation: ShippingOperation, location: Option<GeoPoint>| {
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                operation,
+                now,
+                location.map(|point| ReadPoint {
+                    latitude: point.latitude,
+                    longitude: point.longitude,
+                    altitude: None,
+                    accuracy: None,
+                    source: LocationSource::Gps,
+                }),
+                None,
+                None,
+                None
+            ));
+            ProductTracking::shipment_by_id(&shipment_id).unwrap()
+        };
+
+        // Distance is accumulated between consecutive locations only
+        assert_eq!(
+            track(ShippingOperation::Scan, Some(rotterdam.clone())).distance,
+            0
+        );
+        assert_eq!(track(ShippingOperation::Scan, None).distance, 0);
+        let first_leg = u64::from(geo::distance(&rotterdam, &utrecht).unwrap());
+        let shipment = track(ShippingOperation::Scan, Some(utrecht.clone()));
+        assert_eq!(shipment.distance, first_leg);
+        assert_eq!(shipment.emissions, None);
+
+        // Emissions are estimated upon delivery, ~85km by road at 100g/km
+        let second_leg = u64::from(geo::distance(&utrecht, &amsterdam).unwrap());
+        let shipment = track(ShippingOperation::Deliver, Some(amsterdam));
+        let distance = first_leg + second_leg;
+        assert!(distance > 80_000 && distance < 90_000);
+        assert_eq!(shipment.distance, distance);
+        assert_eq!(shipment.emissions, Some(distance * 100 / 1_000));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::EmissionsEstimated(
+                shipment_id.clone(),
+                TransportMode::Road,
+                distance * 100 / 1_000
+            ))));
+
+        // Transport mode can't be changed once delivered
+        assert_noop!(
+            ProductTracking::set_transport_mode(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                TransportMode::Air
+            ),
+            Error::<Test>::ShipmentIsAwaitingConfirmation
+        );
+    })
+}
+
+#[test]
+fn detect_idle_and_inactive_shipments() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let point = |latitude: f64, longitude: f64| GeoPoint {
+            latitude: Coordinate::from_num(latitude),
+            longitude: Coordinate::from_num(longitude),
+        };
+        let idle_events = || {
+            System::events()
+                .iter()
+                .filter_map(|er| match er.event {
+                    TestEvent::product_tracking(RawEvent::ShipmentIdle(_, duration)) => {
+                        Some(duration)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![test_line(TEST_PRODUCT_ID, 10)],
+            0,
+        );
+
+        let scan = |timestamp: u64, location: GeoPoint| {
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Scan,
+                timestamp,
+                Some(ReadPoint {
+                    latitude: location.latitude,
+                    longitude: location.longitude,
+                    altitude: None,
+                    accuracy: None,
+                    source: LocationSource::Gps,
+                }),
+                None,
+                None,
+                None
+            ));
+        };
+
+        // Scans within the dwell radius, until the dwell limit is exceeded
+        scan(0, point(51.9244, 4.4777));
+        scan(1_800_000, point(51.9250, 4.4780));
+        assert!(idle_events().is_empty());
+        scan(3_700_000, point(51.9240, 4.4770));
+        assert_eq!(idle_events(), vec![3_700_000]);
+
+        // Idle shipment is only reported once
+        scan(4_000_000, point(51.9244, 4.4777));
+        assert_eq!(idle_events(), vec![3_700_000]);
+
+        // Dwell starts over once the shipment moves away
+        scan(5_000_000, point(52.0907, 5.1214));
+        assert_eq!(
+            ProductTracking::dwell_of(&shipment_id),
+            Some(Dwell {
+                point: point(52.0907, 5.1214),
+                since: 5_000_000,
+                reported: false,
+            })
+        );
+
+        // Shipment becomes inactive without any event for IdleTimeout blocks
+        assert_eq!(ProductTracking::last_activity_of(&shipment_id), Some(1));
+        System::set_block_number(10);
+        scan(6_000_000, point(52.0907, 5.1214));
+        let inactive = |block_number: u64| {
+            System::set_block_number(block_number);
+            ProductTracking::on_initialize(block_number);
+            System::events().iter().any(|er| {
+                er.event
+                    == TestEvent::product_tracking(RawEvent::ShipmentInactive(
+                        shipment_id.clone(),
+                        10,
+                    ))
+            })
+        };
+        assert!(!inactive(1 + IdleTimeout::get()));
+        assert!(!inactive(9 + IdleTimeout::get()));
+        assert!(inactive(10 + IdleTimeout::get()));
     })
 }
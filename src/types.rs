@@ -1,31 +1,65 @@
-use codec::{Decode, Encode};
+use crate::geo;
+use codec::{Decode, Encode, HasCompact};
 use core::fmt;
-use fixed::types::I16F16;
+use fixed::types::{I16F16, I32F32, I64F64};
 use frame_support::{sp_runtime::RuntimeDebug, sp_std::prelude::*};
 use product_registry::ProductId;
+use sp_core::{sr25519, H256};
 
 // Custom types
 pub type Identifier = Vec<u8>;
 pub type Decimal = I16F16;
+pub type WideDecimal = I64F64;
+pub type Coordinate = I32F32;
 pub type ShipmentId = Identifier;
 pub type ShippingEventIndex = u128;
 pub type DeviceId = Identifier;
+pub type Quantity = u32;
+pub type LotId = Identifier;
+pub type DevicePublicKey = sr25519::Public;
+pub type DeviceSignature = sr25519::Signature;
+pub type CertificateHash = H256;
+pub type ReadingTypeId = u32;
+pub type FacilityId = Identifier;
+// Cell of the facility index's grid, as (latitude, longitude) in tenths of a degree
+pub type GeoCell = (i16, i16);
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub enum ShipmentStatus {
     Pending,
     InTransit,
     Delivered,
+    Cancelled,
+    Delayed,
+    Damaged,
+    Lost,
+    AwaitingConfirmation,
+    Disputed,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct Shipment<AccountId, Moment> {
     pub id: ShipmentId,
     pub owner: AccountId,
+    pub consignee: AccountId,
     pub status: ShipmentStatus,
-    pub products: Vec<ProductId>,
+    pub products: Vec<ShipmentLine<Moment>>,
     pub registered: Moment,
     pub delivered: Option<Moment>,
+    pub damaged: bool,
+    pub return_of: Option<ShipmentId>,
+    pub returned_as: Option<ShipmentId>,
+    pub delivered_quantities: Vec<Quantity>,
+    pub fulfilment: Option<Fulfilment>,
+    pub recalled: bool,
+    pub compliant: bool,
+    // Number of locations reported outside of the planned route's corridor
+    pub deviations: u32,
+    pub transport_mode: Option<TransportMode>,
+    // Great-circle distance travelled between reported locations, in metres
+    pub distance: u64,
+    // Estimated emissions upon delivery, in grams of CO2e
+    pub emissions: Option<u64>,
 }
 
 impl<AccountId, Moment> Shipment<AccountId, Moment> {
@@ -34,11 +68,102 @@ impl<AccountId, Moment> Shipment<AccountId, Moment> {
         self
     }
 
-    pub fn deliver(mut self, delivered_on: Moment) -> Self {
-        self.status = ShipmentStatus::Delivered;
+    pub fn deliver(mut self, delivered_on: Moment, quantities: Vec<Quantity>) -> Self {
+        let short = self
+            .products
+            .iter()
+            .zip(quantities.iter())
+            .any(|(line, qty)| *qty < line.quantity);
+        let over = self
+            .products
+            .iter()
+            .zip(quantities.iter())
+            .any(|(line, qty)| *qty > line.quantity);
+
+        self.status = ShipmentStatus::AwaitingConfirmation;
         self.delivered = Some(delivered_on);
+        self.delivered_quantities = quantities;
+        self.fulfilment = Some(match (short, over) {
+            (false, false) => Fulfilment::Complete,
+            (true, false) => Fulfilment::ShortShipped,
+            (false, true) => Fulfilment::OverShipped,
+            (true, true) => Fulfilment::Mismatched,
+        });
+        self
+    }
+
+    pub fn confirm_delivery(mut self) -> Self {
+        self.status = ShipmentStatus::Delivered;
+        self
+    }
+
+    pub fn dispute_delivery(mut self) -> Self {
+        self.status = ShipmentStatus::Disputed;
+        self
+    }
+
+    pub fn cancel(mut self) -> Self {
+        self.status = ShipmentStatus::Cancelled;
+        self
+    }
+
+    pub fn report_damage(mut self) -> Self {
+        self.status = ShipmentStatus::Damaged;
+        self.damaged = true;
         self
     }
+
+    pub fn report_loss(mut self) -> Self {
+        self.status = ShipmentStatus::Lost;
+        self
+    }
+
+    pub fn report_delay(mut self) -> Self {
+        self.status = ShipmentStatus::Delayed;
+        self
+    }
+
+    pub fn resume(mut self) -> Self {
+        self.status = ShipmentStatus::InTransit;
+        self
+    }
+
+    pub fn returned_as(mut self, return_id: ShipmentId) -> Self {
+        self.returned_as = Some(return_id);
+        self
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum UnitOfMeasure {
+    Each,
+    Case,
+    Pallet,
+    Kilogram,
+    Litre,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ShipmentLine<Moment> {
+    pub product_id: ProductId,
+    pub quantity: Quantity,
+    pub unit: UnitOfMeasure,
+    pub lot: Option<LotId>,
+    pub expiry: Option<Moment>,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Fulfilment {
+    Complete,
+    ShortShipped,
+    OverShipped,
+    Mismatched,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct DeliveryNote {
+    pub quantities: Vec<Quantity>,
+    pub override_expiry: bool,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -46,6 +171,9 @@ pub enum ShippingOperation {
     Pickup,
     Scan,
     Deliver,
+    ReportDamage,
+    ReportLoss,
+    ReportDelay,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -54,6 +182,27 @@ pub enum ShippingEventType {
     ShipmentPickup,
     ShipmentScan,
     ShipmentDeliver,
+    ShipmentCancellation(CancellationReason),
+    ShipmentDamageReport,
+    ShipmentLossReport,
+    ShipmentDelayReport,
+    ShipmentReturn,
+    ShipmentHandoverOffer,
+    ShipmentHandoverAcceptance,
+    ShipmentDeliveryConfirmation,
+    ShipmentRecall,
+    ShipmentRecallClearance,
+    ShipmentArrival,
+    ShipmentDeparture,
+    ShipmentDeliveryDispute,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum CancellationReason {
+    Duplicate,
+    OrderCancelled,
+    IncorrectDetails,
+    Other,
 }
 
 impl From<ShippingOperation> for ShippingEventType {
@@ -62,6 +211,9 @@ impl From<ShippingOperation> for ShippingEventType {
             ShippingOperation::Pickup => ShippingEventType::ShipmentPickup,
             ShippingOperation::Scan => ShippingEventType::ShipmentScan,
             ShippingOperation::Deliver => ShippingEventType::ShipmentDeliver,
+            ShippingOperation::ReportDamage => ShippingEventType::ShipmentDamageReport,
+            ShippingOperation::ReportLoss => ShippingEventType::ShipmentLossReport,
+            ShippingOperation::ReportDelay => ShippingEventType::ShipmentDelayReport,
         }
     }
 }
@@ -71,8 +223,34 @@ pub struct ShippingEvent<Moment> {
     pub event_type: ShippingEventType,
     pub shipment_id: ShipmentId,
     pub location: Option<ReadPoint>,
+    pub facility: Option<FacilityId>,
     pub readings: Vec<Reading<Moment>>,
+    pub annotations: Vec<ReadingAnnotation>,
+    pub timestamp: Moment,
+}
+
+// What was captured during a shipping operation, before it's validated & stored as shipping events
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ShippingEventInput<Moment> {
     pub timestamp: Moment,
+    pub location: Option<ReadPoint>,
+    pub readings: Vec<Reading<Moment>>,
+    pub delivery_note: Option<DeliveryNote>,
+    pub facility: Option<FacilityId>,
+}
+
+impl<Moment: Clone> ShippingEvent<Moment> {
+    // Readings with their corrected values, when annotated
+    pub fn corrected_readings(&self) -> Vec<Reading<Moment>> {
+        self.readings
+            .iter()
+            .enumerate()
+            .map(|(i, reading)| match self.annotations.get(i) {
+                Some(annotation) => reading.corrected(annotation),
+                None => reading.clone(),
+            })
+            .collect()
+    }
 }
 
 impl<Moment> fmt::Display for ShippingEvent<Moment>
@@ -86,8 +264,176 @@ where
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct ReadPoint {
-    pub latitude: Decimal,
-    pub longitude: Decimal,
+    pub latitude: Coordinate,
+    pub longitude: Coordinate,
+    // Altitude above sea level, in metres
+    pub altitude: Option<Decimal>,
+    // Horizontal accuracy radius, in metres
+    pub accuracy: Option<Decimal>,
+    pub source: LocationSource,
+}
+
+impl ReadPoint {
+    pub fn position(&self) -> GeoPoint {
+        GeoPoint {
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.position().is_valid()
+            && self
+                .accuracy
+                .map_or(true, |accuracy| accuracy >= Decimal::from_num(0))
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum LocationSource {
+    Gps,
+    Cell,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct GeoPoint {
+    pub latitude: Coordinate,
+    pub longitude: Coordinate,
+}
+
+impl GeoPoint {
+    pub fn is_valid(&self) -> bool {
+        self.latitude >= Coordinate::from_num(-90)
+            && self.latitude <= Coordinate::from_num(90)
+            && self.longitude >= Coordinate::from_num(-180)
+            && self.longitude <= Coordinate::from_num(180)
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum TransportMode {
+    Road,
+    Rail,
+    Sea,
+    Air,
+}
+
+// Emission factors of each transport mode, in grams of CO2e per kilometre
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct EmissionFactors {
+    pub road: u32,
+    pub rail: u32,
+    pub sea: u32,
+    pub air: u32,
+}
+
+impl EmissionFactors {
+    pub fn of(&self, mode: &TransportMode) -> u32 {
+        match mode {
+            TransportMode::Road => self.road,
+            TransportMode::Rail => self.rail,
+            TransportMode::Sea => self.sea,
+            TransportMode::Air => self.air,
+        }
+    }
+
+    // Emissions over a distance in metres, in grams of CO2e
+    pub fn emissions(&self, mode: &TransportMode, distance: u64) -> u64 {
+        u64::from(self.of(mode)).saturating_mul(distance) / 1_000
+    }
+}
+
+// Planned route of a shipment, & the width of the corridor around it, in metres
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Route {
+    pub waypoints: Vec<GeoPoint>,
+    pub corridor: u32,
+}
+
+impl Route {
+    pub fn is_valid(&self) -> bool {
+        !self.waypoints.is_empty()
+            && self.waypoints.iter().all(|waypoint| waypoint.is_valid())
+            && self.corridor > 0
+    }
+
+    // Distance from a point to the route, if it's outside of the corridor
+    pub fn deviation(&self, point: &GeoPoint) -> Option<u32> {
+        geo::distance_to_route(&self.waypoints, point).filter(|distance| *distance > self.corridor)
+    }
+}
+
+// Last known position of a shipment, & the facility it was at
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct LastPosition<Moment> {
+    pub point: GeoPoint,
+    pub facility: Option<FacilityId>,
+    pub timestamp: Moment,
+}
+
+// Place around which a shipment has been scanned since a given time
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Dwell<Moment> {
+    pub point: GeoPoint,
+    pub since: Moment,
+    // Whether the shipment has already been reported as idle
+    pub reported: bool,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Geofence {
+    // Circle around a centre, with a radius in metres
+    Radius(GeoPoint, u32),
+    // Polygon given by its vertices, in order
+    Polygon(Vec<GeoPoint>),
+}
+
+impl Geofence {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Geofence::Radius(centre, radius) => centre.is_valid() && *radius > 0,
+            Geofence::Polygon(vertices) => {
+                vertices.len() >= 3 && vertices.iter().all(|vertex| vertex.is_valid())
+            }
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        match self {
+            Geofence::Radius(centre, radius) => {
+                geo::distance(centre, point).map_or(false, |distance| distance <= *radius)
+            }
+            Geofence::Polygon(vertices) => geo::polygon_contains(vertices, point),
+        }
+    }
+
+    // Area in square metres, used to pick the most specific of overlapping facilities
+    pub fn area(&self) -> Option<u64> {
+        match self {
+            Geofence::Radius(_, radius) => geo::circle_area(*radius),
+            Geofence::Polygon(vertices) => geo::polygon_area(vertices),
+        }
+    }
+
+    // Grid cells overlapped by the geofence, unless there are more than `max` of them
+    pub fn cells(&self, max: usize) -> Option<Vec<GeoCell>> {
+        let (south_west, north_east) = match self {
+            Geofence::Radius(centre, radius) => geo::circle_bounds(centre, *radius)?,
+            Geofence::Polygon(vertices) => geo::polygon_bounds(vertices)?,
+        };
+        geo::cells_between(&south_west, &north_east, max)
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Facility<AccountId, Moment> {
+    // GS1 Global Location Number
+    pub id: FacilityId,
+    pub owner: AccountId,
+    // Hash of the facility's postal address, kept off-chain
+    pub address_hash: H256,
+    pub geofence: Geofence,
+    pub registered: Moment,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -98,6 +444,156 @@ pub enum ReadingType {
     Tilt,
     Temperature,
     Vibration,
+    // Reading type registered in the pallet's custom reading types
+    Custom(ReadingTypeId),
+}
+
+impl ReadingType {
+    // Dimension of the built-in reading types
+    pub fn dimension(&self) -> Option<Dimension> {
+        match self {
+            ReadingType::Humidity => Some(Dimension::Ratio),
+            ReadingType::Pressure => Some(Dimension::Pressure),
+            ReadingType::Shock | ReadingType::Vibration => Some(Dimension::Acceleration),
+            ReadingType::Tilt => Some(Dimension::Angle),
+            ReadingType::Temperature => Some(Dimension::Temperature),
+            ReadingType::Custom(_) => None,
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct CustomReadingType {
+    pub name: Vec<u8>,
+    pub dimension: Dimension,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Dimension {
+    Acceleration,
+    Angle,
+    Concentration,
+    Illuminance,
+    Pressure,
+    Ratio,
+    Temperature,
+    Dimensionless,
+}
+
+impl Dimension {
+    // Values of the same dimension are compared in its canonical unit
+    pub fn canonical_unit(&self) -> ReadingUnit {
+        match self {
+            Dimension::Acceleration => ReadingUnit::StandardGravity,
+            Dimension::Angle => ReadingUnit::Degree,
+            Dimension::Concentration => ReadingUnit::PartsPerMillion,
+            Dimension::Illuminance => ReadingUnit::Lux,
+            Dimension::Pressure => ReadingUnit::Hectopascal,
+            Dimension::Ratio => ReadingUnit::Percent,
+            Dimension::Temperature => ReadingUnit::Celsius,
+            Dimension::Dimensionless => ReadingUnit::Unitless,
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum ReadingUnit {
+    // Acceleration
+    StandardGravity,
+    MetrePerSecondSquared,
+    // Angle
+    Degree,
+    // Concentration
+    PartsPerMillion,
+    // Illuminance
+    Lux,
+    // Pressure
+    Hectopascal,
+    Kilopascal,
+    PoundPerSquareInch,
+    // Ratio
+    Percent,
+    // Temperature
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+    // Dimensionless (e.g. counts, open/closed states)
+    Unitless,
+}
+
+impl ReadingUnit {
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            ReadingUnit::StandardGravity | ReadingUnit::MetrePerSecondSquared => {
+                Dimension::Acceleration
+            }
+            ReadingUnit::Degree => Dimension::Angle,
+            ReadingUnit::PartsPerMillion => Dimension::Concentration,
+            ReadingUnit::Lux => Dimension::Illuminance,
+            ReadingUnit::Hectopascal
+            | ReadingUnit::Kilopascal
+            | ReadingUnit::PoundPerSquareInch => Dimension::Pressure,
+            ReadingUnit::Percent => Dimension::Ratio,
+            ReadingUnit::Celsius | ReadingUnit::Fahrenheit | ReadingUnit::Kelvin => {
+                Dimension::Temperature
+            }
+            ReadingUnit::Unitless => Dimension::Dimensionless,
+        }
+    }
+
+    // Converts a value to the canonical unit of its dimension, unless it overflows
+    pub fn to_canonical(&self, value: Decimal) -> Option<Decimal> {
+        // Scales the value by a ratio of integers, in wide precision so that it doesn't overflow
+        let scale = |numerator: i64, denominator: i64| -> Option<Decimal> {
+            let wide = WideDecimal::from_num(value)
+                .checked_mul(WideDecimal::from_num(numerator))?
+                .checked_div(WideDecimal::from_num(denominator))?;
+            Decimal::checked_from_num(wide)
+        };
+        match self {
+            // 1 g = 9.80665 m/s²
+            ReadingUnit::MetrePerSecondSquared => scale(100_000, 980_665),
+            ReadingUnit::Kilopascal => value.checked_mul(Decimal::from_num(10)),
+            // 1 psi = 68.9476 hPa
+            ReadingUnit::PoundPerSquareInch => scale(689_476, 10_000),
+            ReadingUnit::Fahrenheit => value
+                .checked_sub(Decimal::from_num(32))?
+                .checked_mul(Decimal::from_num(5))?
+                .checked_div(Decimal::from_num(9)),
+            // 0 °C = 273.15 K
+            ReadingUnit::Kelvin => {
+                value.checked_sub(Decimal::from_num(27_315) / Decimal::from_num(100))
+            }
+            _ => Some(value),
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum DeviceStatus {
+    Active,
+    Decommissioned,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Device<AccountId, Moment> {
+    pub id: DeviceId,
+    pub owner: AccountId,
+    pub reading_types: Vec<ReadingType>,
+    pub public_key: Option<DevicePublicKey>,
+    pub status: DeviceStatus,
+    pub registered: Moment,
+}
+
+impl<AccountId, Moment> Device<AccountId, Moment> {
+    pub fn decommission(mut self) -> Self {
+        self.status = DeviceStatus::Decommissioned;
+        self
+    }
+
+    pub fn supports(&self, reading_type: &ReadingType) -> bool {
+        self.reading_types.contains(reading_type)
+    }
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -107,4 +603,197 @@ pub struct Reading<Moment> {
     #[codec(compact)]
     pub timestamp: Moment,
     pub value: Decimal,
+    pub unit: ReadingUnit,
+    pub signature: Option<DeviceSignature>,
+}
+
+impl<Moment: Clone> Reading<Moment> {
+    // Reading w/ its calibration-corrected value, in the canonical unit of its dimension
+    pub fn corrected(&self, annotation: &ReadingAnnotation) -> Self {
+        Reading {
+            value: annotation.corrected_value,
+            unit: self.unit.dimension().canonical_unit(),
+            ..self.clone()
+        }
+    }
+}
+
+impl<Moment: Clone + HasCompact> Reading<Moment> {
+    // The device signs the SCALE encoding of the shipment's ID & of the reading, without its
+    // signature, so that the reading can't be replayed for another shipment
+    pub fn signing_payload(&self, shipment_id: &[u8]) -> Vec<u8> {
+        let reading = Reading {
+            signature: None,
+            ..self.clone()
+        };
+        (shipment_id, reading).encode()
+    }
+}
+
+// Readings relayed by an IoT gateway, signed by a registered device
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingsPayload<Moment> {
+    pub signer: DeviceId,
+    pub shipment_id: ShipmentId,
+    pub nonce: u64,
+    #[codec(compact)]
+    pub timestamp: Moment,
+    pub location: Option<ReadPoint>,
+    pub readings: Vec<Reading<Moment>>,
+}
+
+// Pallet-computed metadata of a reading, stored alongside the reading in its shipping event
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingAnnotation {
+    pub verified: bool,
+    // Index of the device's calibration in force at the reading's timestamp
+    pub calibration: Option<u32>,
+    pub calibration_expired: bool,
+    // Calibrated value, in the canonical unit of the reading's dimension
+    pub corrected_value: Decimal,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Calibration<Moment> {
+    #[codec(compact)]
+    pub calibrated: Moment,
+    #[codec(compact)]
+    pub valid_until: Moment,
+    pub offset: Decimal,
+    pub scale: Decimal,
+    pub certificate: CertificateHash,
+}
+
+impl<Moment: PartialOrd> Calibration<Moment> {
+    pub fn has_expired_at(&self, timestamp: &Moment) -> bool {
+        &self.valid_until < timestamp
+    }
+
+    pub fn correct(&self, value: Decimal) -> Decimal {
+        value.saturating_mul(self.scale).saturating_add(self.offset)
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum CalibrationPolicy {
+    // Readings from devices with an expired calibration are accepted but flagged
+    Flag,
+    // Readings from devices with an expired calibration are rejected
+    Reject,
+}
+
+// Physically plausible values of a reading type
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingBounds {
+    pub min: Decimal,
+    pub max: Decimal,
+}
+
+impl ReadingBounds {
+    pub fn contain(&self, value: Decimal) -> bool {
+        self.min <= value && value <= self.max
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ThresholdRule {
+    pub reading_type: ReadingType,
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+}
+
+impl ThresholdRule {
+    pub fn is_breached_by(&self, value: Decimal) -> bool {
+        self.min.map_or(false, |min| value < min) || self.max.map_or(false, |max| value > max)
+    }
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Excursion<Moment> {
+    pub event_idx: ShippingEventIndex,
+    pub device_id: DeviceId,
+    pub reading_type: ReadingType,
+    pub value: Decimal,
+    #[codec(compact)]
+    pub timestamp: Moment,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingSummary<Moment> {
+    pub reading_type: ReadingType,
+    pub count: u32,
+    pub min: Decimal,
+    pub max: Decimal,
+    pub mean: Decimal,
+    #[codec(compact)]
+    pub time_out_of_range: Moment,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ComplianceCertificate<Moment> {
+    pub shipment_id: ShipmentId,
+    pub compliant: bool,
+    pub summaries: Vec<ReadingSummary<Moment>>,
+    pub excursions: u32,
+    #[codec(compact)]
+    pub issued: Moment,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingAggregate<Moment> {
+    pub count: u32,
+    pub min: Decimal,
+    pub max: Decimal,
+    pub sum: WideDecimal,
+    #[codec(compact)]
+    pub first: Moment,
+    #[codec(compact)]
+    pub last: Moment,
+}
+
+impl<Moment: Copy + Ord> ReadingAggregate<Moment> {
+    pub fn new(reading: &Reading<Moment>) -> Self {
+        ReadingAggregate {
+            count: 1,
+            min: reading.value,
+            max: reading.value,
+            sum: WideDecimal::from_num(reading.value),
+            first: reading.timestamp,
+            last: reading.timestamp,
+        }
+    }
+
+    pub fn including(self, reading: &Reading<Moment>) -> Self {
+        ReadingAggregate {
+            count: self.count.saturating_add(1),
+            min: self.min.min(reading.value),
+            max: self.max.max(reading.value),
+            sum: self
+                .sum
+                .saturating_add(WideDecimal::from_num(reading.value)),
+            first: self.first.min(reading.timestamp),
+            last: self.last.max(reading.timestamp),
+        }
+    }
+
+    pub fn mean(&self) -> Decimal {
+        Decimal::from_num(self.sum / WideDecimal::from_num(self.count))
+    }
+}
+
+// Batch of readings kept off-chain, anchored by the Merkle root of their SCALE encodings
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingBatch<Moment> {
+    pub root: H256,
+    pub count: u32,
+    #[codec(compact)]
+    pub from: Moment,
+    #[codec(compact)]
+    pub to: Moment,
+}
+
+impl<Moment: PartialOrd> ReadingBatch<Moment> {
+    pub fn covers(&self, timestamp: &Moment) -> bool {
+        &self.from <= timestamp && timestamp <= &self.to
+    }
 }